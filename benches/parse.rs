@@ -0,0 +1,69 @@
+//! Compares parsing into `HttpRequestRef` with parsing into `HttpRequest`, counting
+//! allocations with a wrapping allocator. Run with `cargo bench --bench parse`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use http::request::{parse_one, HttpRequest, HttpRequestRef, Method};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const REQUEST: &str = "POST /api/items?page=2 HTTP/1.1\r\nHost: localhost:3000\r\nUser-Agent: curl/8.4.0\r\nAccept: */*\r\nAccept-Encoding: gzip, br\r\nContent-Type: application/json\r\nContent-Length: 25\r\nConnection: keep-alive\r\nCookie: session=abc; theme=dark\r\n\r\n{\"name\":\"widget\",\"qty\":3}";
+
+const ITERATIONS: u32 = 200_000;
+
+/// Allocations per parse and nanoseconds per parse, averaged over `ITERATIONS`.
+fn measure(parse: impl Fn(&str)) -> (usize, u128) {
+	let before = ALLOCATIONS.load(Ordering::Relaxed);
+	let start = Instant::now();
+	for _ in 0..ITERATIONS {
+		parse(black_box(REQUEST));
+	}
+	let elapsed = start.elapsed().as_nanos() / ITERATIONS as u128;
+	let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - before) / ITERATIONS as usize;
+
+	(allocations, elapsed)
+}
+
+fn main() {
+	// Both parsers have to take the whole request, or the timings measure an error path.
+	let (req, consumed) = parse_one(REQUEST.as_bytes()).unwrap();
+	assert_eq!(REQUEST.len(), consumed);
+	let borrowed = HttpRequestRef::from(REQUEST);
+	assert_eq!(Method::Post, borrowed.method);
+	assert_eq!(req.msg_body, borrowed.msg_body);
+
+	let (ref_allocs, ref_ns) = measure(|buf| {
+		black_box(HttpRequestRef::from(buf));
+	});
+	let (owned_allocs, owned_ns) = measure(|buf| {
+		black_box(HttpRequest::from(buf.to_string()));
+	});
+
+	println!(
+		"HttpRequestRef::from   {:>3} allocations  {:>6} ns",
+		ref_allocs, ref_ns
+	);
+	println!(
+		"HttpRequest::from      {:>3} allocations  {:>6} ns",
+		owned_allocs, owned_ns
+	);
+}
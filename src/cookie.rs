@@ -0,0 +1,333 @@
+//! Cookies as sent in `Set-Cookie` headers.
+
+use std::fmt::Display;
+
+/// Value of the `SameSite` cookie attribute.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SameSite {
+	Strict,
+	Lax,
+	None
+}
+
+/// Attributes of a cookie set with `HttpResponse::add_cookie`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CookieOptions {
+	pub path: Option<String>,
+	pub domain: Option<String>,
+	/// Lifetime in seconds, zero or negative expires the cookie right away.
+	pub max_age: Option<i64>,
+	/// The `Expires` date, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`.
+	pub expires: Option<String>,
+	pub secure: bool,
+	pub http_only: bool,
+	pub same_site: Option<SameSite>
+}
+
+/// Why a cookie can't be sent, see `Cookie::validate`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CookieError {
+	/// The name is empty or isn't a token.
+	InvalidName,
+	/// The value has whitespace, a control character, `"`, `,`, `;` or `\\`.
+	InvalidValue,
+	/// `Path`, `Domain` or `Expires` has a control character or `;`, or `Path` or
+	/// `Domain` has whitespace or `,`.
+	InvalidAttribute
+}
+
+impl Display for CookieError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::InvalidName => f.write_str("invalid cookie name"),
+			Self::InvalidValue => f.write_str("invalid cookie value"),
+			Self::InvalidAttribute => f.write_str("invalid cookie attribute")
+		}
+	}
+}
+
+impl std::error::Error for CookieError {}
+
+/// A cookie with its `Set-Cookie` attributes.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Cookie {
+	pub name: String,
+	pub value: String,
+	pub path: Option<String>,
+	pub domain: Option<String>,
+	/// Lifetime in seconds, zero or negative expires the cookie right away.
+	pub max_age: Option<i64>,
+	/// The `Expires` date, as sent.
+	pub expires: Option<String>,
+	pub secure: bool,
+	pub http_only: bool,
+	pub same_site: Option<SameSite>
+}
+
+impl Cookie {
+	/// A session cookie with no attributes set.
+	pub fn new(name: &str, value: &str) -> Self {
+		Cookie {
+			name: name.to_string(),
+			value: value.to_string(),
+			..Default::default()
+		}
+	}
+
+	/// A cookie with the attributes in `options`.
+	pub fn with_options(name: &str, value: &str, options: CookieOptions) -> Self {
+		Cookie {
+			name: name.to_string(),
+			value: value.to_string(),
+			path: options.path,
+			domain: options.domain,
+			max_age: options.max_age,
+			expires: options.expires,
+			secure: options.secure,
+			http_only: options.http_only,
+			same_site: options.same_site
+		}
+	}
+
+	/// Checks that the cookie can be sent as is (RFC 6265 §4.1.1). The fields are
+	/// written verbatim, so a `;` or line break would add attributes or headers.
+	/// ```
+	/// use http::cookie::{Cookie, CookieError};
+	///
+	/// assert_eq!(Ok(()), Cookie::new("id", "\"a1\"").validate());
+	/// assert_eq!(Err(CookieError::InvalidValue), Cookie::new("id", "a1; Domain=evil.example").validate());
+	/// ```
+	pub fn validate(&self) -> Result<(), CookieError> {
+		let is_tchar = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+		if self.name.is_empty() || !self.name.bytes().all(is_tchar) {
+			return Err(CookieError::InvalidName);
+		}
+
+		let value = self
+			.value
+			.strip_prefix('"')
+			.and_then(|v| v.strip_suffix('"'))
+			.unwrap_or(&self.value);
+		let is_cookie_octet =
+			|b: u8| matches!(b, 0x21 | 0x23..=0x2b | 0x2d..=0x3a | 0x3c..=0x5b | 0x5d..=0x7e);
+		if !value.bytes().all(is_cookie_octet) {
+			return Err(CookieError::InvalidValue);
+		}
+
+		let is_av_octet = |b: u8| (b' '..=b'~').contains(&b) && b != b';';
+		let is_location_octet = |b: u8| is_av_octet(b) && b != b' ' && b != b',';
+		let valid = |attr: &Option<String>, octet: &dyn Fn(u8) -> bool| {
+			attr.as_ref().is_none_or(|v| v.bytes().all(octet))
+		};
+		if !valid(&self.path, &is_location_octet)
+			|| !valid(&self.domain, &is_location_octet)
+			|| !valid(&self.expires, &is_av_octet)
+		{
+			return Err(CookieError::InvalidAttribute);
+		}
+
+		Ok(())
+	}
+
+	/// Parses a `Set-Cookie` header value. Attribute names are matched ignoring case,
+	/// unknown attributes and invalid attribute values are skipped. Returns `None`
+	/// if there's no `name=value` pair or the name is empty.
+	/// ```
+	/// use http::cookie::{Cookie, SameSite};
+	///
+	/// let cookie = Cookie::parse("id=a3fWa; Max-Age=2592000; HttpOnly; SameSite=Lax").unwrap();
+	/// assert_eq!(("id", "a3fWa"), (cookie.name.as_str(), cookie.value.as_str()));
+	/// assert_eq!(Some(2592000), cookie.max_age);
+	/// assert!(cookie.http_only);
+	/// assert_eq!(Some(SameSite::Lax), cookie.same_site);
+	/// ```
+	pub fn parse(value: &str) -> Option<Cookie> {
+		let mut parts = value.split(';');
+		let (name, value) = parts.next()?.split_once('=')?;
+		let name = name.trim();
+		if name.is_empty() {
+			return None;
+		}
+
+		let mut cookie = Cookie {
+			name: name.to_string(),
+			value: value.trim().to_string(),
+			..Default::default()
+		};
+
+		for attr in parts {
+			let (key, val) = attr.split_once('=').unwrap_or((attr, ""));
+			let (key, val) = (key.trim(), val.trim());
+
+			match key.to_ascii_lowercase().as_str() {
+				"path" => cookie.path = Some(val.to_string()),
+				"domain" => cookie.domain = Some(val.trim_start_matches('.').to_string()),
+				"max-age" => cookie.max_age = val.parse().ok(),
+				"expires" => cookie.expires = Some(val.to_string()),
+				"secure" => cookie.secure = true,
+				"httponly" => cookie.http_only = true,
+				"samesite" => {
+					cookie.same_site = match val.to_ascii_lowercase().as_str() {
+						"strict" => Some(SameSite::Strict),
+						"lax" => Some(SameSite::Lax),
+						"none" => Some(SameSite::None),
+						_ => None
+					}
+				}
+				_ => {}
+			}
+		}
+
+		Some(cookie)
+	}
+}
+
+/// The `Set-Cookie` header value, e.g. `id=42; Path=/; HttpOnly`.
+impl Display for Cookie {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}={}", self.name, self.value)?;
+
+		if let Some(path) = &self.path {
+			write!(f, "; Path={}", path)?;
+		}
+		if let Some(domain) = &self.domain {
+			write!(f, "; Domain={}", domain)?;
+		}
+		if let Some(max_age) = self.max_age {
+			write!(f, "; Max-Age={}", max_age)?;
+		}
+		if let Some(expires) = &self.expires {
+			write!(f, "; Expires={}", expires)?;
+		}
+		if self.secure {
+			f.write_str("; Secure")?;
+		}
+		if self.http_only {
+			f.write_str("; HttpOnly")?;
+		}
+		match self.same_site {
+			Some(SameSite::Strict) => f.write_str("; SameSite=Strict"),
+			Some(SameSite::Lax) => f.write_str("; SameSite=Lax"),
+			Some(SameSite::None) => f.write_str("; SameSite=None"),
+			None => Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::cookie::*;
+
+	#[test]
+	fn test_parse_set_cookie() {
+		let cookie = Cookie::parse(
+			"session=abc=123; path=/app; DOMAIN=.example.com; Max-Age=3600; Expires=Wed, 21 Oct 2015 07:28:00 GMT; secure; HTTPONLY; SameSite=Strict; Priority=High"
+		)
+		.unwrap();
+
+		assert_eq!(
+			Cookie {
+				name: "session".into(),
+				value: "abc=123".into(),
+				path: Some("/app".into()),
+				domain: Some("example.com".into()),
+				max_age: Some(3600),
+				expires: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+				secure: true,
+				http_only: true,
+				same_site: Some(SameSite::Strict)
+			},
+			cookie
+		);
+	}
+
+	#[test]
+	fn test_parse_set_cookie_invalid() {
+		assert_eq!(None, Cookie::parse(""));
+		assert_eq!(None, Cookie::parse("novalue; Path=/"));
+		assert_eq!(None, Cookie::parse("=value"));
+
+		let cookie = Cookie::parse("a=; Max-Age=soon; SameSite=Sometimes").unwrap();
+		assert_eq!("", cookie.value);
+		assert_eq!(None, cookie.max_age);
+		assert_eq!(None, cookie.same_site);
+	}
+
+	#[test]
+	fn test_validate() {
+		let cookie = Cookie::with_options(
+			"session",
+			"abc123",
+			CookieOptions {
+				path: Some("/app".into()),
+				expires: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+				..Default::default()
+			}
+		);
+		assert_eq!(Ok(()), cookie.validate());
+
+		assert_eq!(
+			Err(CookieError::InvalidName),
+			Cookie::new("", "v").validate()
+		);
+		assert_eq!(
+			Err(CookieError::InvalidName),
+			Cookie::new("a b", "v").validate()
+		);
+		assert_eq!(
+			Err(CookieError::InvalidName),
+			Cookie::new("a=b", "v").validate()
+		);
+
+		for value in [
+			"a b",
+			"a,b",
+			"a;b",
+			"a\r\nX-Injected: 1",
+			"\"a",
+			"a\\b",
+			"é"
+		] {
+			assert_eq!(
+				Err(CookieError::InvalidValue),
+				Cookie::new("a", value).validate()
+			);
+		}
+
+		let with = |path: &str, expires: &str| Cookie {
+			path: Some(path.into()),
+			expires: Some(expires.into()),
+			..Cookie::new("a", "b")
+		};
+		assert_eq!(
+			Err(CookieError::InvalidAttribute),
+			with("/; Secure", "").validate()
+		);
+		assert_eq!(
+			Err(CookieError::InvalidAttribute),
+			with("/a b", "").validate()
+		);
+		assert_eq!(
+			Err(CookieError::InvalidAttribute),
+			with("/", "x\r\ny").validate()
+		);
+	}
+
+	#[test]
+	fn test_display_round_trip() {
+		let cookie = Cookie {
+			path: Some("/".into()),
+			max_age: Some(60),
+			secure: true,
+			http_only: true,
+			same_site: Some(SameSite::None),
+			..Cookie::new("csrf", "t0k3n")
+		};
+
+		assert_eq!(
+			"csrf=t0k3n; Path=/; Max-Age=60; Secure; HttpOnly; SameSite=None",
+			cookie.to_string()
+		);
+		assert_eq!(Some(cookie.clone()), Cookie::parse(&cookie.to_string()));
+	}
+}
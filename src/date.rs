@@ -0,0 +1,124 @@
+//! Calendar conversions for the date formats used in HTTP and access logs, all in UTC.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+	"Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"
+];
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Broken-down UTC time: year, month (1-12), day, hour, minute, second.
+struct DateTime(i64, usize, i64, i64, i64, i64);
+
+fn to_datetime(time: SystemTime) -> DateTime {
+	let secs = time
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs() as i64);
+	let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+	// Days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+
+	DateTime(
+		year,
+		month as usize,
+		day,
+		rem / 3600,
+		rem % 3600 / 60,
+		rem % 60
+	)
+}
+
+/// Formats `time` as used in the common log format, e.g. `10/Oct/2000:13:55:36 +0000`.
+pub(crate) fn format_clf(time: SystemTime) -> String {
+	let DateTime(year, month, day, hour, min, sec) = to_datetime(time);
+	format!(
+		"{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+		day,
+		MONTHS[month - 1],
+		year,
+		hour,
+		min,
+		sec
+	)
+}
+
+/// Parses an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub(crate) fn parse_http_date(s: &str) -> Option<SystemTime> {
+	let (weekday, rest) = s.trim().split_once(", ")?;
+	if !WEEKDAYS.contains(&weekday) {
+		return None;
+	}
+
+	let mut parts = rest.split(' ');
+	let day: i64 = parts.next().filter(|d| d.len() == 2)?.parse().ok()?;
+	let month = parts.next()?;
+	let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+	let year: i64 = parts.next().filter(|y| y.len() == 4)?.parse().ok()?;
+	let time = parts.next()?;
+	if parts.next() != Some("GMT") || parts.next().is_some() {
+		return None;
+	}
+
+	let mut hms = time
+		.split(':')
+		.map(|t| t.parse::<i64>().ok().filter(|_| t.len() == 2));
+	let (hour, min, sec) = (hms.next()??, hms.next()??, hms.next()??);
+	if hms.next().is_some() || !(1..=31).contains(&day) || hour > 23 || min > 59 || sec > 60 {
+		return None;
+	}
+
+	// Civil date to days since the epoch, the inverse of `to_datetime`.
+	let y = year - i64::from(month <= 2);
+	let era = y.div_euclid(400);
+	let yoe = y - era * 400;
+	let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	let days = era * 146097 + doe - 719468;
+
+	let secs = days * 86400 + hour * 3600 + min * 60 + sec;
+	Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::date::*;
+
+	#[test]
+	fn test_format_clf() {
+		assert_eq!("01/Jan/1970:00:00:00 +0000", format_clf(UNIX_EPOCH));
+		assert_eq!(
+			"10/Oct/2000:13:55:36 +0000",
+			format_clf(UNIX_EPOCH + Duration::from_secs(971186136))
+		);
+		assert_eq!(
+			"29/Feb/2024:23:59:59 +0000",
+			format_clf(UNIX_EPOCH + Duration::from_secs(1709251199))
+		);
+	}
+
+	#[test]
+	fn test_parse_http_date() {
+		assert_eq!(
+			Some(UNIX_EPOCH + Duration::from_secs(784111777)),
+			parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")
+		);
+		assert_eq!(
+			Some(UNIX_EPOCH + Duration::from_secs(1709251199)),
+			parse_http_date("Thu, 29 Feb 2024 23:59:59 GMT")
+		);
+		assert_eq!(None, parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"));
+		assert_eq!(None, parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"));
+		assert_eq!(None, parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"));
+		assert_eq!(None, parse_http_date("yesterday"));
+	}
+}
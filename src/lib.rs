@@ -1,4 +1,9 @@
 //! Squioole http library
 
+pub mod cookie;
+mod date;
 pub mod request;
 pub mod response;
+mod sha256;
+pub mod sse;
+pub mod url;
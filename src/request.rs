@@ -1,10 +1,136 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, time::SystemTime};
+
+use crate::date::parse_http_date;
+use crate::sha256::{hex, sha256};
+use crate::url::{
+	decode_charset, encode_pairs, parse_urlencoded, parse_urlencoded_bytes, percent_decode
+};
 
 /// Resource requested
 #[derive(Debug, PartialEq, Clone)]
 pub enum Resource {
 	/// A path for a subpage
-	Path(String)
+	Path(String),
+	/// A full URL such as `http://example.com/a`, as sent to proxies
+	Absolute(String),
+	/// A `host:port`, the target of a `CONNECT`
+	Authority(String),
+	/// `*`, for an `OPTIONS` request about the whole server
+	Asterisk
+}
+
+impl Resource {
+	/// The request target as it appeared in the request line.
+	pub fn as_str(&self) -> &str {
+		match self {
+			Self::Path(p) | Self::Absolute(p) | Self::Authority(p) => p,
+			Self::Asterisk => "*"
+		}
+	}
+
+	/// The path of the target, without the query string or fragment. For an absolute
+	/// URL that's the part after the authority, `/` if it has none.
+	pub fn path(&self) -> &str {
+		let mut target = self.without_fragment();
+		if let Self::Absolute(_) = self {
+			let rest = target.split_once("://").map_or(target, |(_, rest)| rest);
+			target = rest.find(['/', '?']).map_or("", |i| &rest[i..]);
+		}
+
+		match target.split_once('?').map_or(target, |(path, _)| path) {
+			"" if matches!(self, Self::Absolute(_)) => "/",
+			path => path
+		}
+	}
+
+	/// The raw query string after the first `?`, if any, without the fragment.
+	pub fn query(&self) -> Option<&str> {
+		self
+			.without_fragment()
+			.split_once('?')
+			.map(|(_, query)| query)
+	}
+
+	/// The part after the first `#`, if any. Clients shouldn't send fragments, but
+	/// some proxies and tools do.
+	/// ```
+	/// use http::request::Resource;
+	///
+	/// let res = Resource::Path("/docs?v=2#install".to_string());
+	/// assert_eq!(Some("install"), res.fragment());
+	/// assert_eq!("/docs", res.path());
+	/// assert_eq!(Some("v=2"), res.query());
+	/// ```
+	pub fn fragment(&self) -> Option<&str> {
+		self.as_str().split_once('#').map(|(_, fragment)| fragment)
+	}
+
+	fn without_fragment(&self) -> &str {
+		let target = self.as_str();
+		target.split_once('#').map_or(target, |(rest, _)| rest)
+	}
+
+	/// Builds a `path?key=value&...` target, percent-encoding the parameters.
+	/// ```
+	/// use http::request::Resource;
+	///
+	/// let target = Resource::with_query("/search", &[("q", "rust http"), ("page", "2")]);
+	/// assert_eq!("/search?q=rust%20http&page=2", target);
+	/// ```
+	pub fn with_query(path: &str, params: &[(&str, &str)]) -> String {
+		if params.is_empty() {
+			path.to_string()
+		} else {
+			format!("{}?{}", path, encode_pairs(params))
+		}
+	}
+
+	/// Removes a mount prefix such as `/api` from the path, keeping the query, for
+	/// routing inside a mounted sub-application. Only whole segments match, so
+	/// `/apix` doesn't start with `/api`. Returns `None` if the prefix doesn't match.
+	/// ```
+	/// use http::request::Resource;
+	///
+	/// let res = Resource::Path("/api/users?page=2".to_string());
+	/// assert_eq!(Some(Resource::Path("/users?page=2".to_string())), res.strip_prefix("/api"));
+	/// ```
+	pub fn strip_prefix(&self, prefix: &str) -> Option<Resource> {
+		let rest = self.as_str().strip_prefix(prefix.trim_end_matches('/'))?;
+
+		match rest.chars().next() {
+			Some('/') => Some(Resource::Path(rest.to_string())),
+			None | Some('?') | Some('#') => Some(Resource::Path(format!("/{}", rest))),
+			Some(_) => None
+		}
+	}
+
+	/// Whether the target has a `%` that isn't followed by two hex digits.
+	/// Such a target can't be decoded reliably and should get a `400 Bad Request`.
+	pub fn has_invalid_percent_encoding(&self) -> bool {
+		let bytes = self.as_str().as_bytes();
+
+		bytes.iter().enumerate().any(|(i, b)| {
+			*b == b'%'
+				&& !(bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+					&& bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit))
+		})
+	}
+}
+
+/// Sorts a request target into its form: `*`, a path, an absolute URL (with a
+/// `scheme://`) or otherwise an authority. An empty target is an empty path.
+impl From<&str> for Resource {
+	fn from(s: &str) -> Resource {
+		if s == "*" {
+			Resource::Asterisk
+		} else if s.starts_with('/') || s.is_empty() {
+			Resource::Path(s.to_string())
+		} else if s.contains("://") {
+			Resource::Absolute(s.to_string())
+		} else {
+			Resource::Authority(s.to_string())
+		}
+	}
 }
 
 /// Http Request struct.
@@ -16,7 +142,7 @@ pub enum Resource {
 ///
 /// let req: HttpRequest = raw_request.into();
 /// let mut headers_expected = HashMap::new();
-/// headers_expected.insert("Host".into(), "localhost".into());
+/// headers_expected.insert("Host".into(), "localhost:3000".into());
 /// headers_expected.insert("Accept".into(), "*/*".into());
 /// headers_expected.insert("User-Agent".into(), "rust".into());
 /// assert_eq!(Method::Get, req.method);
@@ -31,164 +157,2275 @@ pub struct HttpRequest {
 	pub version: Version,
 	pub resource: Resource,
 	pub headers: HashMap<String, String>,
+	/// Every header line in the order received, repeated names included.
+	/// `headers` only keeps the last value of each name. `header`, `header_all` and
+	/// `to_raw_bytes` read this list, so a header added or removed after parsing
+	/// has to be changed here as well.
+	pub header_list: Vec<(String, String)>,
 	pub msg_body: String
 }
 
+/// Lenient parsing: a missing or malformed request line gives a request with default
+/// fields instead of an error. Use `HttpRequest::parse_strict` to reject it.
 impl From<String> for HttpRequest {
 	fn from(req: String) -> Self {
-		let mut parsed_method = Method::Unknown("".into());
-		let mut parsed_version = Version::V1_1;
-		let mut parsed_resource = Resource::Path("".to_string());
-		let mut parsed_headers = HashMap::new();
-		let mut parsed_msg_body = "".to_string();
-		let mut in_body = false;
-
-		for line in req.lines() {
-			if !in_body {
-				if line.contains("HTTP") {
-					let (method, resource, version) = process_req_line(line);
-					parsed_method = method;
-					parsed_version = version;
-					parsed_resource = resource;
-				} else if line.contains(':') {
-					let (key, value) = process_header_line(line);
-					parsed_headers.insert(key, value);
-				} else if line.is_empty() {
-					// Blank line. Next lines will be processed as the body.
-					in_body = true;
+		HttpRequestRef::from(req.as_str()).into_owned()
+	}
+}
+
+/// A parsed request borrowing the target, headers and body from the input buffer,
+/// so parsing doesn't allocate a `String` for each of them. Only the header list
+/// (and the name of an unknown method) is allocated. Parses like
+/// `From<String> for HttpRequest`, which goes through it; use `into_owned` to get
+/// an `HttpRequest`.
+///
+/// On a `POST` with nine headers and a small body, `cargo bench --bench parse`
+/// measured 2 allocations and about 0.5 µs per parse, against 39 allocations and
+/// about 1.4 µs for `HttpRequest` (including the `String` it's parsed from).
+/// ```
+/// use http::request::{HttpRequestRef, Method};
+///
+/// let buf = "POST /items HTTP/1.1\r\nHost: localhost:3000\r\n\r\n{}";
+/// let req = HttpRequestRef::from(buf);
+/// assert_eq!(Method::Post, req.method);
+/// assert_eq!(("/items", Some("localhost:3000"), "{}"), (req.resource, req.header("host"), req.msg_body));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpRequestRef<'a> {
+	pub method: Method,
+	pub version: Version,
+	/// The request target as sent.
+	pub resource: &'a str,
+	/// Header names and values in the order received.
+	pub headers: Vec<(&'a str, &'a str)>,
+	/// The body as sent; chunked coding is only decoded by `into_owned`.
+	pub msg_body: &'a str
+}
+
+impl<'a> From<&'a str> for HttpRequestRef<'a> {
+	fn from(req: &'a str) -> Self {
+		let mut parsed = HttpRequestRef {
+			method: Method::Unknown("".into()),
+			version: Version::V1_1,
+			resource: "",
+			headers: Vec::new(),
+			msg_body: ""
+		};
+		let mut offset = 0;
+
+		for (i, raw_line) in req.split_inclusive('\n').enumerate() {
+			offset += raw_line.len();
+			let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+			let line = line.strip_suffix('\r').unwrap_or(line);
+
+			if i == 0 && line.contains("HTTP") {
+				// A malformed request line keeps the defaults, use `try_from` to reject it.
+				if let Ok((method, resource, version)) = process_req_line(line) {
+					parsed.method = method;
+					parsed.version = version;
+					parsed.resource = resource;
 				}
-			} else {
-				parsed_msg_body.push_str(line);
+			} else if line.contains(':') {
+				parsed.headers.push(process_header_line(line));
+			} else if line.is_empty() {
+				// Blank line. The rest of the input is the body, line breaks included.
+				parsed.msg_body = &req[offset..];
+				break;
 			}
 		}
 
-		HttpRequest {
-			method: parsed_method,
-			version: parsed_version,
-			resource: parsed_resource,
-			headers: parsed_headers,
-			msg_body: parsed_msg_body.trim_end_matches('\u{0}').into()
-		}
+		parsed.msg_body = parsed.msg_body.trim_end_matches('\u{0}');
+		parsed
 	}
 }
 
-fn process_req_line(s: &str) -> (Method, Resource, Version) {
-	let mut words = s.split_whitespace();
-	let method = words.next().unwrap();
-	let resource = words.next().unwrap();
-	let version = words.next().unwrap();
+impl<'a> HttpRequestRef<'a> {
+	/// Value of the header `name`, ignoring ASCII case. For a repeated header that's
+	/// the last value received, whatever the case of its name. `HttpRequest::headers`
+	/// is keyed by the exact spelling, so it can keep an earlier value sent as `host`
+	/// next to a later `Host`.
+	pub fn header(&self, name: &str) -> Option<&'a str> {
+		self
+			.headers
+			.iter()
+			.rev()
+			.find(|(k, _)| k.eq_ignore_ascii_case(name))
+			.map(|(_, v)| *v)
+	}
 
-	(
-		method.into(),
-		Resource::Path(resource.to_string()),
-		version.into()
-	)
-}
+	/// Copies the request into an owned `HttpRequest`, decoding a chunked body.
+	pub fn into_owned(self) -> HttpRequest {
+		let header_list: Vec<(String, String)> = self
+			.headers
+			.iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect();
 
-fn process_header_line(s: &str) -> (String, String) {
-	let mut header_items = s.split(':');
-	let mut key = String::from("");
-	let mut value = String::from("");
+		let mut parsed = HttpRequest {
+			method: self.method,
+			version: self.version,
+			resource: self.resource.into(),
+			headers: header_list.iter().cloned().collect(),
+			header_list,
+			msg_body: self.msg_body.to_string()
+		};
+
+		// A body that isn't valid chunked coding is kept as sent.
+		if parsed.is_chunked() {
+			if let Ok((body, _)) = decode_chunked(parsed.msg_body.as_bytes(), &ParseOptions::default()) {
+				parsed.msg_body = String::from_utf8_lossy(&body).into_owned();
+			}
+		}
 
-	if let Some(k) = header_items.next() {
-		key = k.to_string();
+		parsed
 	}
+}
+
+/// Strict parsing, same as `HttpRequest::parse_strict`.
+///
+/// Only `&str` is covered: `HttpRequest::try_from(String)` resolves to the standard
+/// library's blanket impl over `From<String>`, which never fails and parses
+/// leniently. Pass `buf.as_str()` or call `parse_strict` instead.
+/// ```
+/// use http::request::{HttpRequest, ParseError};
+///
+/// assert!(HttpRequest::try_from("GET / HTTP/1.1\r\n\r\n").is_ok());
+/// assert_eq!(Err(ParseError::MissingVersion), HttpRequest::try_from("GET /\r\n\r\n").map(|_| ()));
+/// assert!(HttpRequest::try_from(String::from("garbage\r\n\r\n")).is_ok());
+/// ```
+impl TryFrom<&str> for HttpRequest {
+	type Error = ParseError;
 
-	if let Some(v) = header_items.next() {
-		value = v.to_string().trim_start().to_string()
+	fn try_from(req: &str) -> Result<Self, Self::Error> {
+		Self::parse_strict(req)
 	}
+}
+
+/// The request in wire format, with headers sorted by name so the output is
+/// deterministic. Use `to_raw_bytes` to keep the received order and repeats.
+///
+/// A request without a method, target or version, as left by the lenient parser
+/// for a malformed request line, has no wire form: formatting it is an error, so
+/// `to_string` panics and the `String` conversion gives an empty string.
+/// ```
+/// use http::request::HttpRequest;
+///
+/// let req: HttpRequest = String::from("POST /a HTTP/1.1\r\nHost: x\r\nAccept: */*\r\n\r\nhi").into();
+/// assert_eq!("POST /a HTTP/1.1\r\nAccept: */*\r\nHost: x\r\n\r\nhi", req.to_string());
+///
+/// let garbage: HttpRequest = String::from("garbage\r\n\r\n").into();
+/// assert_eq!("", String::from(garbage));
+/// ```
+impl Display for HttpRequest {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let method = self.method.to_string();
+		let target = self.resource.as_str();
+		if method.is_empty() || target.is_empty() || self.version == Version::Uninitialized {
+			return Err(std::fmt::Error);
+		}
 
-	(key, value)
+		let mut headers: Vec<_> = self.headers.iter().collect();
+		headers.sort();
+
+		write!(f, "{} {} {}\r\n", method, target, self.version)?;
+		for (k, v) in headers {
+			write!(f, "{}: {}\r\n", k, v)?;
+		}
+		write!(f, "\r\n{}", self.msg_body)
+	}
 }
 
-/// Http method
-#[derive(Debug, PartialEq, Clone)]
-pub enum Method {
-	Get,
-	Post,
-	Head,
-	Put,
-	Delete,
-	Connect,
-	Options,
-	Trace,
-	Patch,
-	Unknown(String)
+impl From<HttpRequest> for String {
+	fn from(req: HttpRequest) -> String {
+		use std::fmt::Write;
+
+		let mut wire = String::new();
+		if write!(wire, "{}", req).is_err() {
+			wire.clear();
+		}
+		wire
+	}
 }
 
-impl Display for Method {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		if let Self::Unknown(m) = self {
-			f.write_str(m)
+impl HttpRequest {
+	/// Strict parsing: unlike `From<String>`, a missing or malformed request line is
+	/// an error instead of a request with default fields. The request line must be
+	/// exactly three tokens separated by single spaces, so stray whitespace such as
+	/// `GET / HTTP/1.1 ` is rejected where the lenient parser would ignore it.
+	/// ```
+	/// use http::request::{HttpRequest, ParseError};
+	///
+	/// assert!(HttpRequest::parse_strict("GET / HTTP/1.1\r\n\r\n").is_ok());
+	/// assert_eq!(Err(ParseError::MissingVersion), HttpRequest::parse_strict("GET /\r\n\r\n").map(|_| ()));
+	/// ```
+	pub fn parse_strict(req: &str) -> Result<Self, ParseError> {
+		let line = req.lines().next().unwrap_or("");
+		if line.trim().is_empty() {
+			return Err(ParseError::EmptyRequest);
+		}
+
+		let (method, resource, version) = process_req_line(line)?;
+		if line.split(' ').count() != 3 {
+			return Err(ParseError::MalformedRequestLine);
+		}
+
+		let mut parsed: HttpRequest = req.to_string().into();
+		parsed.method = method;
+		parsed.resource = resource.into();
+		parsed.version = version;
+
+		Ok(parsed)
+	}
+
+	/// Value of the header `name`, ignoring ASCII case in the name as header names
+	/// are case-insensitive. For a repeated header that's the last line received,
+	/// whatever the case of its name, so the answer doesn't depend on map order.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET / HTTP/1.1\r\nContent-Type: text/plain\r\n\r\n").into();
+	/// assert_eq!(Some("text/plain"), req.header("content-type"));
+	/// ```
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self
+			.header_list
+			.iter()
+			.rev()
+			.find(|(k, _)| k.eq_ignore_ascii_case(name))
+			.map(|(_, v)| v.as_str())
+	}
+
+	/// All values of the header `name` in the order they were received, ignoring
+	/// ASCII case in the name. Repeated headers such as `X-Forwarded-For` are all kept.
+	pub fn header_all(&self, name: &str) -> Vec<&str> {
+		self
+			.header_list
+			.iter()
+			.filter(|(k, _)| k.eq_ignore_ascii_case(name))
+			.map(|(_, v)| v.as_str())
+			.collect()
+	}
+
+	/// The request rebuilt for replay, with headers in the order and spelling they
+	/// were received, repeats included. The parser doesn't keep the raw input, so
+	/// this matches it byte for byte only when it used CRLF and `Name: value` lines.
+	pub fn to_raw_bytes(&self) -> Vec<u8> {
+		let mut wire = format!(
+			"{} {} {}\r\n",
+			self.method,
+			self.resource.as_str(),
+			self.version.as_str()
+		);
+		for (k, v) in &self.header_list {
+			wire += &format!("{}: {}\r\n", k, v);
+		}
+		wire += "\r\n";
+
+		let mut bytes = wire.into_bytes();
+		bytes.extend_from_slice(self.msg_body.as_bytes());
+		bytes
+	}
+
+	/// Checks whether the `Connection` header lists `token`, ignoring case.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET / HTTP/1.1\r\nConnection: Upgrade, keep-alive\r\n\r\n").into();
+	/// assert!(req.connection_has_token("upgrade"));
+	/// assert!(!req.connection_has_token("close"));
+	/// ```
+	pub fn connection_has_token(&self, token: &str) -> bool {
+		match self.header("Connection") {
+			Some(value) => value
+				.split(',')
+				.any(|t| t.trim().eq_ignore_ascii_case(token)),
+			None => false
+		}
+	}
+
+	/// Whether the connection should be kept open after this request.
+	/// HTTP/1.1 connections are persistent unless the client sends `Connection: close`.
+	pub fn keep_alive(&self) -> bool {
+		match self.version {
+			Version::V1_1 => !self.connection_has_token("close"),
+			_ => self.connection_has_token("keep-alive")
+		}
+	}
+
+	/// Builds a deterministic representation of the request for HMAC signing:
+	/// the uppercase method, the target, each of `signed_headers` as a lowercased
+	/// `name:value` line (sorted by name, value trimmed, empty if missing) and the
+	/// hex SHA-256 of the body, all separated by `\n`.
+	pub fn canonical_string(&self, signed_headers: &[&str]) -> String {
+		let mut headers: Vec<(String, &str)> = signed_headers
+			.iter()
+			.map(|name| {
+				(
+					name.to_ascii_lowercase(),
+					self.header(name).unwrap_or("").trim()
+				)
+			})
+			.collect();
+		headers.sort();
+
+		let mut canonical = format!(
+			"{}\n{}\n",
+			self.method.to_string().to_uppercase(),
+			self.resource.as_str()
+		);
+		for (name, value) in headers {
+			canonical += &format!("{}:{}\n", name, value);
+		}
+		canonical + &hex(&sha256(self.msg_body.as_bytes()))
+	}
+
+	/// Parses a single-range `Range: bytes=...` header.
+	/// Multiple ranges and other units aren't supported and yield `None`.
+	pub fn range(&self) -> Option<ByteRange> {
+		let value = self.header("Range")?.trim();
+		let (unit, spec) = value.split_once('=')?;
+		if !unit.trim().eq_ignore_ascii_case("bytes") || spec.contains(',') {
+			return None;
+		}
+
+		let (start, end) = spec.trim().split_once('-')?;
+		match (start.trim(), end.trim()) {
+			("", "") => None,
+			("", suffix) => suffix.parse().ok().map(ByteRange::Suffix),
+			(start, "") => start.parse().ok().map(ByteRange::From),
+			(start, end) => {
+				let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+				(start <= end).then_some(ByteRange::FromTo(start, end))
+			}
+		}
+	}
+
+	/// Parses the `Cache-Control` directives into lowercased names and their optional
+	/// (unquoted) values, e.g. `no-cache, max-age=0` gives `no-cache` → `None` and
+	/// `max-age` → `Some("0")`.
+	pub fn cache_control(&self) -> HashMap<String, Option<String>> {
+		let mut directives = HashMap::new();
+
+		for directive in self.header("Cache-Control").unwrap_or("").split(',') {
+			let (name, value) = match directive.split_once('=') {
+				Some((name, value)) => (name, Some(value.trim().trim_matches('"').to_string())),
+				None => (directive, None)
+			};
+
+			let name = name.trim();
+			if !name.is_empty() {
+				directives.insert(name.to_ascii_lowercase(), value);
+			}
+		}
+
+		directives
+	}
+
+	/// The method the request should be handled as. HTML forms can only send GET and
+	/// POST, so a POST may name the intended method in `X-HTTP-Method-Override`.
+	/// Overrides on any other method, or naming an unknown method, are ignored.
+	pub fn effective_method(&self) -> Method {
+		if self.method != Method::Post {
+			return self.method.clone();
+		}
+
+		match self
+			.header("X-HTTP-Method-Override")
+			.map(|m| Method::from(m.trim()))
+		{
+			Some(Method::Unknown(_)) | None => self.method.clone(),
+			Some(method) => method
+		}
+	}
+
+	/// Parses `Authorization: Digest ...` credentials. Returns `None` for other schemes
+	/// or when a required parameter (`username`, `realm`, `nonce`, `uri`, `response`) is missing.
+	pub fn digest_auth(&self) -> Option<DigestAuth> {
+		let value = self.header("Authorization")?.trim();
+		let (scheme, params) = value.split_once(' ')?;
+		if !scheme.eq_ignore_ascii_case("Digest") {
+			return None;
+		}
+
+		let mut params = parse_auth_params(params);
+		Some(DigestAuth {
+			username: params.remove("username")?,
+			realm: params.remove("realm")?,
+			nonce: params.remove("nonce")?,
+			uri: params.remove("uri")?,
+			response: params.remove("response")?,
+			qop: params.remove("qop"),
+			nc: params.remove("nc"),
+			cnonce: params.remove("cnonce"),
+			opaque: params.remove("opaque"),
+			algorithm: params.remove("algorithm")
+		})
+	}
+
+	/// The media type from `Content-Type` without its parameters, lowercased,
+	/// e.g. `application/json` for `Application/JSON; charset=utf-8`.
+	pub fn content_type_base(&self) -> Option<String> {
+		let value = self.header("Content-Type")?;
+		let base = value.split(';').next().unwrap_or("").trim();
+		(!base.is_empty()).then(|| base.to_ascii_lowercase())
+	}
+
+	/// Parses the `If-Unmodified-Since` date. If the resource was modified after it,
+	/// the request must not be applied and a `412 Precondition Failed` returned.
+	/// An invalid date yields `None`, in which case the header must be ignored.
+	pub fn if_unmodified_since(&self) -> Option<SystemTime> {
+		parse_http_date(self.header("If-Unmodified-Since")?)
+	}
+
+	/// The headers sorted by name, ignoring case, for display.
+	pub fn sorted_headers(&self) -> Vec<(String, String)> {
+		let mut headers: Vec<(String, String)> = self
+			.headers
+			.iter()
+			.map(|(k, v)| (k.clone(), v.clone()))
+			.collect();
+		headers.sort_by_cached_key(|(k, _)| k.to_ascii_lowercase());
+		headers
+	}
+
+	/// Parses the body as `application/x-www-form-urlencoded` pairs. Works for any
+	/// method, since some clients send form data in the body of a GET.
+	///
+	/// Decoded values are read in the charset named by the `Content-Type` `charset`
+	/// parameter, or else by a `_charset_` form field, defaulting to UTF-8.
+	pub fn form(&self) -> Vec<(String, String)> {
+		let pairs = parse_urlencoded_bytes(&self.msg_body);
+
+		let charset = self
+			.header("Content-Type")
+			.and_then(|ct| {
+				ct.split(';').skip(1).find_map(|param| {
+					let (name, value) = param.split_once('=')?;
+					name
+						.trim()
+						.eq_ignore_ascii_case("charset")
+						.then(|| value.trim().trim_matches('"').to_string())
+				})
+			})
+			.or_else(|| {
+				pairs
+					.iter()
+					.find(|(k, _)| k == b"_charset_")
+					.map(|(_, v)| String::from_utf8_lossy(v).into_owned())
+			})
+			.unwrap_or_else(|| "utf-8".to_string());
+
+		pairs
+			.iter()
+			.map(|(k, v)| (decode_charset(k, &charset), decode_charset(v, &charset)))
+			.collect()
+	}
+
+	/// The path of the request target, without the query string, for routing.
+	pub fn path(&self) -> &str {
+		self.resource.path()
+	}
+
+	/// The path with `%XX` escapes decoded as UTF-8. `+` stays as is, since it only
+	/// means a space in query strings, and malformed escapes such as `%2` are kept.
+	/// Note that `%2F` decodes to `/`, so split the raw path first when segments matter.
+	pub fn decoded_path(&self) -> String {
+		percent_decode(self.path())
+	}
+
+	/// The method, path (without the query) and version, for matching in one go.
+	/// ```
+	/// use http::request::{HttpRequest, Method, Version};
+	///
+	/// let req: HttpRequest = String::from("GET /users?page=2 HTTP/1.1\r\n\r\n").into();
+	/// match req.as_parts() {
+	///     (Method::Get, "/users", Version::V1_1) => {}
+	///     _ => unreachable!()
+	/// }
+	/// ```
+	pub fn as_parts(&self) -> (&Method, &str, &Version) {
+		(&self.method, self.path(), &self.version)
+	}
+
+	/// The decoded query parameters. A key without `=` gets an empty value and
+	/// for a repeated key the last value wins; use `parse_urlencoded` on
+	/// `resource.query()` to get every pair in order.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET /search?q=rust%20http&page=2 HTTP/1.1\r\n\r\n").into();
+	/// assert_eq!("/search", req.path());
+	/// assert_eq!("rust http", req.query()["q"]);
+	/// ```
+	pub fn query(&self) -> HashMap<String, String> {
+		parse_urlencoded(self.resource.query().unwrap_or(""))
+			.into_iter()
+			.collect()
+	}
+
+	/// The cookies sent in `Cookie` headers, by name. Names are trimmed, values keep
+	/// any `=` after the first one, and a pair without `=` is skipped.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET / HTTP/1.1\r\nCookie: id=42; theme=dark\r\n\r\n").into();
+	/// assert_eq!("dark", req.cookies()["theme"]);
+	/// ```
+	pub fn cookies(&self) -> HashMap<String, String> {
+		self
+			.header_all("Cookie")
+			.into_iter()
+			.flat_map(|header| header.split(';'))
+			.filter_map(|pair| {
+				let (name, value) = pair.split_once('=')?;
+				let name = name.trim();
+				(!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+			})
+			.collect()
+	}
+
+	/// The body without the UTF-8 byte order mark some Windows clients put in front
+	/// of text bodies, which would otherwise break JSON parsing.
+	pub fn body_without_bom(&self) -> &str {
+		self
+			.msg_body
+			.strip_prefix('\u{feff}')
+			.unwrap_or(&self.msg_body)
+	}
+
+	/// Whether `Accept-Encoding` allows a response in `coding`, e.g. `gzip`. A coding
+	/// listed with `q=0` is refused, one that isn't listed follows `*`. Without the
+	/// header only `identity` is assumed to be acceptable.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET / HTTP/1.1\r\nAccept-Encoding: gzip, br;q=0\r\n\r\n").into();
+	/// assert!(req.accepts_encoding("gzip"));
+	/// assert!(!req.accepts_encoding("br"));
+	/// ```
+	pub fn accepts_encoding(&self, coding: &str) -> bool {
+		let Some(header) = self.header("Accept-Encoding") else {
+			return coding.eq_ignore_ascii_case("identity");
+		};
+
+		let weights = parse_weighted_list(header);
+		match weight_of(&weights, coding).or_else(|| weight_of(&weights, "*")) {
+			Some(q) => q > 0.0,
+			None => coding.eq_ignore_ascii_case("identity")
+		}
+	}
+
+	/// Picks the best of `supported` according to `Accept-Charset`. A charset the
+	/// header doesn't list gets the weight of `*`, or is refused if there's none;
+	/// ties go to the earlier entry of `supported`. Without the header any charset
+	/// is fine, so the first supported one is returned.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET / HTTP/1.1\r\nAccept-Charset: utf-8, iso-8859-1;q=0.5\r\n\r\n").into();
+	/// assert_eq!(Some("iso-8859-1".to_string()), req.preferred_charset(&["iso-8859-1", "utf-16"]));
+	/// ```
+	pub fn preferred_charset(&self, supported: &[&str]) -> Option<String> {
+		let Some(header) = self.header("Accept-Charset") else {
+			return supported.first().map(|charset| charset.to_string());
+		};
+
+		let weights = parse_weighted_list(header);
+		let weight = |charset: &str| {
+			weight_of(&weights, charset)
+				.or_else(|| weight_of(&weights, "*"))
+				.unwrap_or(0.0)
+		};
+
+		let mut best: Option<(&str, f32)> = None;
+		for charset in supported {
+			let q = weight(charset);
+			if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+				best = Some((charset, q));
+			}
+		}
+
+		best.map(|(charset, _)| charset.to_string())
+	}
+
+	/// The client address, taken from `X-Forwarded-For` only as far as trusted proxies
+	/// vouch for it. If the peer at `socket_addr` isn't a trusted proxy the header
+	/// could be forged and `socket_addr` is returned. Otherwise the chain is walked
+	/// from the right, skipping trusted proxies, and the first other address is the
+	/// client; `socket_addr` is returned if there is none.
+	/// ```
+	/// use http::request::HttpRequest;
+	///
+	/// let req: HttpRequest = String::from("GET / HTTP/1.1\r\nX-Forwarded-For: 1.2.3.4, 10.0.0.2\r\n\r\n").into();
+	/// assert_eq!("1.2.3.4", req.client_ip(&["10.0.0.1", "10.0.0.2"], "10.0.0.1"));
+	/// ```
+	pub fn client_ip(&self, trusted_proxies: &[&str], socket_addr: &str) -> String {
+		if !trusted_proxies.contains(&socket_addr) {
+			return socket_addr.to_string();
+		}
+
+		self
+			.header_all("X-Forwarded-For")
+			.iter()
+			.flat_map(|header| header.split(','))
+			.map(str::trim)
+			.filter(|hop| !hop.is_empty())
+			.rev()
+			.find(|hop| !trusted_proxies.contains(hop))
+			.unwrap_or(socket_addr)
+			.to_string()
+	}
+
+	/// Whether the request was sent through `XMLHttpRequest`, as signalled by
+	/// `X-Requested-With: XMLHttpRequest`.
+	pub fn is_xhr(&self) -> bool {
+		self
+			.header("X-Requested-With")
+			.is_some_and(|v| v.trim().eq_ignore_ascii_case("XMLHttpRequest"))
+	}
+
+	/// Whether the request can be retried safely after a failure: its method is
+	/// idempotent, or it carries an `Idempotency-Key` the server deduplicates on.
+	pub fn is_retryable(&self) -> bool {
+		self.method.is_idempotent()
+			|| self
+				.header("Idempotency-Key")
+				.is_some_and(|key| !key.trim().is_empty())
+	}
+
+	/// Checks that the target form is allowed for the method: `CONNECT` needs an
+	/// authority, `OPTIONS` may also use `*` and every other method takes a path or
+	/// an absolute URL.
+	/// ```
+	/// use http::request::{HttpRequest, ParseError};
+	///
+	/// let req: HttpRequest = String::from("CONNECT /tunnel HTTP/1.1\r\n\r\n").into();
+	/// assert_eq!(Err(ParseError::InvalidTargetForm), req.validate_target_form());
+	/// ```
+	pub fn validate_target_form(&self) -> Result<(), ParseError> {
+		let valid = match (&self.method, &self.resource) {
+			(Method::Connect, Resource::Authority(_)) => true,
+			(Method::Connect, _) => false,
+			(Method::Options, Resource::Asterisk) => true,
+			(_, Resource::Path(_) | Resource::Absolute(_)) => true,
+			_ => false
+		};
+
+		if valid {
+			Ok(())
 		} else {
-			f.write_str(&format!("{:?}", self).to_uppercase())
+			Err(ParseError::InvalidTargetForm)
 		}
 	}
+
+	/// Whether the body uses chunked transfer coding, i.e. it's the last coding
+	/// listed in `Transfer-Encoding`.
+	fn is_chunked(&self) -> bool {
+		self.header("Transfer-Encoding").is_some_and(|te| {
+			te.rsplit(',')
+				.next()
+				.unwrap_or("")
+				.trim()
+				.eq_ignore_ascii_case("chunked")
+		})
+	}
+
+	/// Whether the client asks to upgrade the connection to a WebSocket.
+	pub fn is_websocket_upgrade(&self) -> bool {
+		self.connection_has_token("upgrade")
+			&& self
+				.header("Upgrade")
+				.is_some_and(|u| u.trim().eq_ignore_ascii_case("websocket"))
+	}
 }
 
-impl From<&str> for Method {
-	fn from(s: &str) -> Method {
-		match s {
-			"GET" => Method::Get,
-			"POST" => Method::Post,
-			"HEAD" => Method::Head,
-			"OPTIONS" => Method::Options,
-			"TRACE" => Method::Trace,
-			"PATCH" => Method::Patch,
-			_ => Method::Unknown(s.into())
+/// Byte range requested through the `Range` header.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ByteRange {
+	/// `bytes=start-end`, both inclusive
+	FromTo(u64, u64),
+	/// `bytes=start-`, from `start` to the end of the resource
+	From(u64),
+	/// `bytes=-len`, the last `len` bytes of the resource
+	Suffix(u64)
+}
+
+impl ByteRange {
+	/// Resolves the range against a resource of `total` bytes into `[start, end)` bounds,
+	/// or `None` if it can't be satisfied (a `416 Range Not Satisfiable`).
+	/// ```
+	/// use http::request::ByteRange;
+	///
+	/// assert_eq!(Some((500, 1000)), ByteRange::Suffix(500).resolve(1000));
+	/// assert_eq!(Some((0, 1000)), ByteRange::Suffix(5000).resolve(1000));
+	/// assert_eq!(None, ByteRange::From(1000).resolve(1000));
+	/// ```
+	pub fn resolve(&self, total: u64) -> Option<(u64, u64)> {
+		match *self {
+			Self::FromTo(start, end) if start < total => Some((start, total.min(end.saturating_add(1)))),
+			Self::From(start) if start < total => Some((start, total)),
+			Self::Suffix(len) if len > 0 && total > 0 => Some((total.saturating_sub(len), total)),
+			_ => None
 		}
 	}
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum Version {
-	V1_1,
-	V2_0,
-	Uninitialized
+/// How to answer a request that may carry `Range`, see `resolve_range_response`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RangeDecision {
+	/// Send the whole resource with `200 OK`.
+	Full,
+	/// Send `[start, end)` with `206 Partial Content`.
+	Partial(u64, u64),
+	/// Answer with `416 Range Not Satisfiable`.
+	NotSatisfiable
 }
 
-impl From<&str> for Version {
-	fn from(s: &str) -> Version {
-		match s {
-			"HTTP/1.1" => Version::V1_1,
-			"HTTP/2.0" => Version::V2_0,
-			_ => Version::Uninitialized
+/// Decides between a full and a partial response for `req`, given the current
+/// entity tag and length of the resource. An `If-Range` that isn't a strong match
+/// for `current_etag` (including a date, as only the tag is known here) means the
+/// client's copy is stale, so the full resource is sent.
+/// ```
+/// use http::request::{resolve_range_response, HttpRequest, RangeDecision};
+///
+/// let req: HttpRequest = String::from("GET /f HTTP/1.1\r\nRange: bytes=0-9\r\n\r\n").into();
+/// assert_eq!(RangeDecision::Partial(0, 10), resolve_range_response(&req, "\"v1\"", 100));
+/// ```
+pub fn resolve_range_response(
+	req: &HttpRequest,
+	current_etag: &str,
+	total_len: u64
+) -> RangeDecision {
+	let Some(range) = req.range() else {
+		return RangeDecision::Full;
+	};
+
+	if let Some(if_range) = req.header("If-Range") {
+		let if_range = if_range.trim();
+		if if_range.starts_with("W/") || if_range != current_etag.trim() {
+			return RangeDecision::Full;
 		}
 	}
+
+	match range.resolve(total_len) {
+		Some((start, end)) => RangeDecision::Partial(start, end),
+		None => RangeDecision::NotSatisfiable
+	}
 }
 
-#[cfg(test)]
-mod tests {
-	use crate::request::*;
+/// Credentials from an `Authorization: Digest ...` header.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DigestAuth {
+	pub username: String,
+	pub realm: String,
+	pub nonce: String,
+	pub uri: String,
+	pub response: String,
+	pub qop: Option<String>,
+	pub nc: Option<String>,
+	pub cnonce: Option<String>,
+	pub opaque: Option<String>,
+	pub algorithm: Option<String>
+}
 
-	#[test]
-	fn test_method_into() {
-		let m: Method = "GET".into();
-		assert_eq!(m, Method::Get);
+/// Splits `key=value, key="quoted, value"` pairs, unquoting values and lowercasing keys.
+fn parse_auth_params(s: &str) -> HashMap<String, String> {
+	let mut params = HashMap::new();
+	let mut rest = s.trim_start();
+
+	while let Some((key, after)) = rest.split_once('=') {
+		let key = key.trim().to_ascii_lowercase();
+		let after = after.trim_start();
+
+		let (value, next) = match after.strip_prefix('"') {
+			Some(quoted) => {
+				let mut value = String::new();
+				let mut chars = quoted.char_indices();
+				let mut end = quoted.len();
+				while let Some((i, c)) = chars.next() {
+					match c {
+						'\\' => value.extend(chars.next().map(|(_, c)| c)),
+						'"' => {
+							end = i + 1;
+							break;
+						}
+						_ => value.push(c)
+					}
+				}
+				(value, &quoted[end..])
+			}
+			None => {
+				let end = after.find(',').unwrap_or(after.len());
+				(after[..end].trim().to_string(), &after[end..])
+			}
+		};
+
+		params.insert(key, value);
+		rest = next.trim_start().trim_start_matches(',').trim_start();
 	}
 
-	#[test]
-	fn test_version_into() {
-		let m: Version = "HTTP/1.1".into();
-		let m2: Version = "HTTP/2.0".into();
-		let m3: Version = "321dshaui".into();
-		assert_eq!(m, Version::V1_1);
-		assert_eq!(m2, Version::V2_0);
-		assert_eq!(m3, Version::Uninitialized);
+	params
+}
+
+/// Error returned when a request can't be parsed from a buffer.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+	/// The buffer doesn't hold a full request yet.
+	Incomplete,
+	/// The request head or body isn't valid UTF-8.
+	InvalidUtf8,
+	/// The `Content-Length` header isn't a valid length.
+	InvalidContentLength,
+	/// The request target is longer than `ParseOptions::max_uri_bytes`.
+	/// Should be answered with `414 URI Too Long`.
+	UriTooLong,
+	/// The body framing is ambiguous: both `Transfer-Encoding` and `Content-Length`
+	/// are present, or the last transfer coding isn't `chunked`. Proxies may disagree
+	/// on where such a request ends, so it must be rejected rather than guessed.
+	SmugglingRisk,
+	/// The request line doesn't have a method and a target.
+	MalformedRequestLine,
+	/// There is no request line at all.
+	EmptyRequest,
+	/// The request line has no HTTP version.
+	MissingVersion,
+	/// The target form doesn't fit the method, e.g. a `CONNECT` to a path.
+	InvalidTargetForm,
+	/// A chunked body has an invalid chunk size or a chunk not followed by CRLF.
+	InvalidChunk,
+	/// The method isn't one of the standard ones.
+	UnknownMethod,
+	/// The head, a chunk size line or the trailers are longer than
+	/// `ParseOptions::max_header_bytes`. Should be answered with
+	/// `431 Request Header Fields Too Large`.
+	HeadersTooLarge,
+	/// A chunked body has more chunks than `ParseOptions::max_chunks`.
+	TooManyChunks
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Incomplete => f.write_str("incomplete request"),
+			Self::InvalidUtf8 => f.write_str("request is not valid UTF-8"),
+			Self::InvalidContentLength => f.write_str("invalid Content-Length header"),
+			Self::UriTooLong => f.write_str("request target is too long"),
+			Self::SmugglingRisk => f.write_str("ambiguous request body framing"),
+			Self::MalformedRequestLine => f.write_str("malformed request line"),
+			Self::EmptyRequest => f.write_str("empty request"),
+			Self::MissingVersion => f.write_str("request line has no HTTP version"),
+			Self::InvalidTargetForm => f.write_str("request target form doesn't match the method"),
+			Self::InvalidChunk => f.write_str("invalid chunked body"),
+			Self::UnknownMethod => f.write_str("unknown request method"),
+			Self::HeadersTooLarge => f.write_str("request headers are too large"),
+			Self::TooManyChunks => f.write_str("too many chunks in request body")
+		}
 	}
+}
 
-	#[test]
-	fn test_read_http() {
-		let s: String = String::from("GET /greeting HTTP/1.1\r\nHost: localhost:3000\r\nUser-Agent: curl/7.64.1\r\nAccept: */*\r\n\r\ntestbody123");
-		let mut headers_expected = HashMap::new();
-		headers_expected.insert("Host".into(), "localhost".into());
-		headers_expected.insert("Accept".into(), "*/*".into());
-		headers_expected.insert("User-Agent".into(), "curl/7.64.1".into());
-		let req: HttpRequest = s.into();
-		assert_eq!(Method::Get, req.method);
-		assert_eq!(Version::V1_1, req.version);
-		assert_eq!(Resource::Path("/greeting".to_string()), req.resource);
-		assert_eq!(headers_expected, req.headers);
-		assert_eq!("testbody123", req.msg_body);
+impl std::error::Error for ParseError {}
+
+/// Parses a comma-separated list of `token;q=0.x` entries, as used by `Accept` and
+/// the other negotiation headers, into tokens and weights. A missing `q` defaults to
+/// 1.0, out-of-range weights are clamped to `[0, 1]` and entries with an invalid `q`
+/// are skipped.
+/// ```
+/// use http::request::parse_weighted_list;
+///
+/// let list = parse_weighted_list("gzip, br;q=0.8");
+/// assert_eq!(vec![("gzip".to_string(), 1.0), ("br".to_string(), 0.8)], list);
+/// ```
+pub fn parse_weighted_list(header: &str) -> Vec<(String, f32)> {
+	header
+		.split(',')
+		.filter_map(|entry| {
+			let mut params = entry.split(';');
+			let token = params.next()?.trim();
+			if token.is_empty() {
+				return None;
+			}
+
+			let mut q = 1.0;
+			for param in params {
+				if let Some((name, value)) = param.split_once('=') {
+					if name.trim().eq_ignore_ascii_case("q") {
+						q = value.trim().parse::<f32>().ok().filter(|q| !q.is_nan())?;
+					}
+				}
+			}
+
+			Some((token.to_string(), q.clamp(0.0, 1.0)))
+		})
+		.collect()
+}
+
+/// Weight of `token` in a list from `parse_weighted_list`, ignoring case.
+fn weight_of(weights: &[(String, f32)], token: &str) -> Option<f32> {
+	weights
+		.iter()
+		.find(|(name, _)| name.eq_ignore_ascii_case(token))
+		.map(|(_, q)| *q)
+}
+
+/// Whether `input` starts with the HTTP/2 connection preface a client sends when it
+/// speaks h2c with prior knowledge. Check it before parsing to hand the connection
+/// over to an HTTP/2 handler.
+pub fn is_h2_preface(input: &[u8]) -> bool {
+	input.starts_with(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+}
+
+/// Limits applied by `parse_one_with`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseOptions {
+	/// Longest request target accepted, in bytes. Defaults to 8000.
+	pub max_uri_bytes: usize,
+	/// Longest head accepted, in bytes. Also bounds each chunk size line, with its
+	/// extensions, and the trailers of a chunked body. Defaults to 16384.
+	pub max_header_bytes: usize,
+	/// Most chunks accepted in a chunked body, the last one included. Defaults to 4096.
+	pub max_chunks: usize
+}
+
+impl Default for ParseOptions {
+	fn default() -> Self {
+		Self {
+			max_uri_bytes: 8000,
+			max_header_bytes: 16384,
+			max_chunks: 4096
+		}
+	}
+}
+
+/// Parses a single request from the start of `input`, returning it together with
+/// the number of bytes it took up (head and body). Any bytes after that belong to
+/// the next request, so a connection reader can advance its buffer by that amount.
+/// ```
+/// use http::request::{parse_one, Resource};
+///
+/// let buf = b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\n";
+/// let (first, used) = parse_one(buf).unwrap();
+/// let (second, _) = parse_one(&buf[used..]).unwrap();
+/// assert_eq!(Resource::Path("/a".to_string()), first.resource);
+/// assert_eq!(Resource::Path("/b".to_string()), second.resource);
+/// ```
+pub fn parse_one(input: &[u8]) -> Result<(HttpRequest, usize), ParseError> {
+	parse_one_with(input, &ParseOptions::default()).map(|(req, stats)| (req, stats.consumed))
+}
+
+/// Where a request parsed by `parse_one_with` ended in its buffer.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ParseStats {
+	/// Bytes taken up by the request, head and body.
+	pub consumed: usize,
+	/// Bytes left in the buffer after the request, e.g. a pipelined request.
+	pub bytes_remaining: usize
+}
+
+/// Same as `parse_one`, enforcing the limits in `options` and reporting how much
+/// of `input` is left over, so a connection loop knows whether more requests are pending.
+/// The head is parsed like `HttpRequest::parse_strict`, so a malformed request line
+/// is an error.
+pub fn parse_one_with(
+	input: &[u8],
+	options: &ParseOptions
+) -> Result<(HttpRequest, ParseStats), ParseError> {
+	// Checked before the head is complete so an endless target is cut off early.
+	let line_end = input
+		.windows(2)
+		.position(|w| w == b"\r\n")
+		.unwrap_or(input.len());
+	// Tokens are split like in `process_req_line`, so extra spaces don't hide the target.
+	let target_len = input[..line_end]
+		.split(u8::is_ascii_whitespace)
+		.filter(|token| !token.is_empty())
+		.nth(1)
+		.map_or(0, <[u8]>::len);
+	if target_len > options.max_uri_bytes {
+		return Err(ParseError::UriTooLong);
+	}
+
+	let head_end = match input.windows(4).position(|w| w == b"\r\n\r\n") {
+		Some(end) if end + 4 <= options.max_header_bytes => end + 4,
+		Some(_) => return Err(ParseError::HeadersTooLarge),
+		None if input.len() > options.max_header_bytes => return Err(ParseError::HeadersTooLarge),
+		None => return Err(ParseError::Incomplete)
+	};
+
+	let head = std::str::from_utf8(&input[..head_end]).map_err(|_| ParseError::InvalidUtf8)?;
+	let mut req = HttpRequest::parse_strict(head)?;
+
+	// Repeats that proxies could resolve differently are rejected rather than picked from.
+	let lengths = req.header_all("Content-Length");
+	if lengths.iter().any(|len| len.trim() != lengths[0].trim()) {
+		return Err(ParseError::InvalidContentLength);
+	}
+	if req.header_all("Transfer-Encoding").len() > 1 || req.header_all("Host").len() > 1 {
+		return Err(ParseError::SmugglingRisk);
+	}
+
+	if req.header("Transfer-Encoding").is_some() {
+		if req.header("Content-Length").is_some() || !req.is_chunked() {
+			return Err(ParseError::SmugglingRisk);
+		}
+
+		let (body, consumed) = decode_chunked(&input[head_end..], options)?;
+		let end = head_end + consumed;
+		req.msg_body = String::from_utf8(body).map_err(|_| ParseError::InvalidUtf8)?;
+
+		return Ok((
+			req,
+			ParseStats {
+				consumed: end,
+				bytes_remaining: input.len() - end
+			}
+		));
+	}
+
+	let body_len = match req.header("Content-Length").map(str::trim) {
+		// `parse` would also take a `+`, the grammar only allows `1*DIGIT`.
+		Some(len) if len.is_empty() || !len.bytes().all(|b| b.is_ascii_digit()) => {
+			return Err(ParseError::InvalidContentLength);
+		}
+		Some(len) => len
+			.parse::<usize>()
+			.map_err(|_| ParseError::InvalidContentLength)?,
+		None => 0
+	};
+
+	// A length past the end of memory can never arrive, it isn't just incomplete.
+	let end = head_end
+		.checked_add(body_len)
+		.ok_or(ParseError::InvalidContentLength)?;
+	if input.len() < end {
+		return Err(ParseError::Incomplete);
+	}
+
+	req.msg_body =
+		String::from_utf8(input[head_end..end].to_vec()).map_err(|_| ParseError::InvalidUtf8)?;
+
+	Ok((
+		req,
+		ParseStats {
+			consumed: end,
+			bytes_remaining: input.len() - end
+		}
+	))
+}
+
+/// Decodes a chunked body at the start of `input`, returning the data and the number
+/// of bytes the chunks, the last chunk and the trailers took. Chunk extensions and
+/// trailer fields are skipped. Lines, chunks and trailers are bounded by `options`,
+/// so a peer can't make a caller wait on an ever growing buffer.
+fn decode_chunked(input: &[u8], options: &ParseOptions) -> Result<(Vec<u8>, usize), ParseError> {
+	let max = options.max_header_bytes;
+	let line_at = |pos: usize| -> Result<(&[u8], usize), ParseError> {
+		match input[pos..].windows(2).position(|w| w == b"\r\n") {
+			Some(len) if len <= max => Ok((&input[pos..pos + len], pos + len + 2)),
+			Some(_) => Err(ParseError::HeadersTooLarge),
+			None if input.len() - pos > max => Err(ParseError::HeadersTooLarge),
+			None => Err(ParseError::Incomplete)
+		}
+	};
+
+	let mut body = Vec::new();
+	let mut pos = 0;
+	for _ in 0..options.max_chunks {
+		let (line, next) = line_at(pos)?;
+		let size = line.split(|b| *b == b';').next().unwrap_or(b"");
+		// `from_str_radix` would also take a sign, the grammar only allows `1*HEXDIG`.
+		let size = size.trim_ascii();
+		if size.is_empty() || !size.iter().all(u8::is_ascii_hexdigit) {
+			return Err(ParseError::InvalidChunk);
+		}
+		let size = std::str::from_utf8(size)
+			.ok()
+			.and_then(|size| usize::from_str_radix(size, 16).ok())
+			.ok_or(ParseError::InvalidChunk)?;
+		pos = next;
+
+		if size == 0 {
+			let trailers_start = pos;
+			loop {
+				let (trailer, next) = line_at(pos)?;
+				pos = next;
+				if trailer.is_empty() {
+					return Ok((body, pos));
+				}
+				if pos - trailers_start > max {
+					return Err(ParseError::HeadersTooLarge);
+				}
+			}
+		}
+
+		if input.len() - pos < size.saturating_add(2) {
+			return Err(ParseError::Incomplete);
+		}
+		let end = pos + size;
+		if &input[end..end + 2] != b"\r\n" {
+			return Err(ParseError::InvalidChunk);
+		}
+
+		body.extend_from_slice(&input[pos..end]);
+		pos = end + 2;
+	}
+
+	Err(ParseError::TooManyChunks)
+}
+
+fn process_req_line(s: &str) -> Result<(Method, &str, Version), ParseError> {
+	let mut words = s.split_whitespace();
+	let (Some(method), Some(resource)) = (words.next(), words.next()) else {
+		return Err(ParseError::MalformedRequestLine);
+	};
+	let version = words.next().ok_or(ParseError::MissingVersion)?;
+	if words.next().is_some() {
+		return Err(ParseError::MalformedRequestLine);
+	}
+
+	Ok((method.into(), resource, version.into()))
+}
+
+fn process_header_line(s: &str) -> (&str, &str) {
+	// Only the first colon separates the name, values like `localhost:3000` keep theirs.
+	let (key, value) = s.split_once(':').unwrap_or((s, ""));
+
+	(key, value.trim_start())
+}
+
+/// Http method
+#[derive(Debug, PartialEq, Clone)]
+pub enum Method {
+	Get,
+	Post,
+	Head,
+	Put,
+	Delete,
+	Connect,
+	Options,
+	Trace,
+	Patch,
+	Unknown(String)
+}
+
+impl Method {
+	/// Whether repeating the request has the same effect as sending it once
+	/// (RFC 9110, section 9.2.2).
+	pub fn is_idempotent(&self) -> bool {
+		matches!(
+			self,
+			Self::Get | Self::Head | Self::Put | Self::Delete | Self::Options | Self::Trace
+		)
+	}
+}
+
+impl Display for Method {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if let Self::Unknown(m) = self {
+			f.write_str(m)
+		} else {
+			f.write_str(&format!("{:?}", self).to_uppercase())
+		}
+	}
+}
+
+/// Lenient conversion, ignoring ASCII case. Anything else becomes `Unknown`.
+impl From<&str> for Method {
+	fn from(s: &str) -> Method {
+		// Compared in place, so parsing a standard method doesn't allocate.
+		[
+			("GET", Method::Get),
+			("POST", Method::Post),
+			("HEAD", Method::Head),
+			("PUT", Method::Put),
+			("DELETE", Method::Delete),
+			("CONNECT", Method::Connect),
+			("OPTIONS", Method::Options),
+			("TRACE", Method::Trace),
+			("PATCH", Method::Patch)
+		]
+		.into_iter()
+		.find(|(name, _)| name.eq_ignore_ascii_case(s))
+		.map_or_else(|| Method::Unknown(s.into()), |(_, method)| method)
+	}
+}
+
+/// Strict conversion, ignoring ASCII case. An unknown method is an error.
+/// ```
+/// use http::request::{Method, ParseError};
+///
+/// assert_eq!(Ok(Method::Patch), "patch".parse());
+/// assert_eq!(Err(ParseError::UnknownMethod), "BREW".parse::<Method>());
+/// ```
+impl std::str::FromStr for Method {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match Method::from(s) {
+			Method::Unknown(_) => Err(ParseError::UnknownMethod),
+			method => Ok(method)
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Version {
+	V1_0,
+	V1_1,
+	V2_0,
+	V3_0,
+	Uninitialized
+}
+
+impl Version {
+	pub(crate) fn as_str(&self) -> &'static str {
+		match self {
+			Self::V1_0 => "HTTP/1.0",
+			Self::V1_1 => "HTTP/1.1",
+			Self::V2_0 => "HTTP/2.0",
+			Self::V3_0 => "HTTP/3.0",
+			Self::Uninitialized => ""
+		}
+	}
+}
+
+/// The version as it appears in a request or status line, e.g. `HTTP/1.1`.
+/// `Uninitialized` writes nothing.
+impl Display for Version {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl From<&str> for Version {
+	fn from(s: &str) -> Version {
+		match s {
+			"HTTP/1.0" => Version::V1_0,
+			"HTTP/1.1" => Version::V1_1,
+			"HTTP/2.0" => Version::V2_0,
+			"HTTP/3.0" => Version::V3_0,
+			_ => Version::Uninitialized
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::request::*;
+
+	#[test]
+	fn test_method_into() {
+		let m: Method = "GET".into();
+		assert_eq!(m, Method::Get);
+	}
+
+	#[test]
+	fn test_method_all_variants() {
+		let methods = [
+			("GET", Method::Get),
+			("POST", Method::Post),
+			("HEAD", Method::Head),
+			("PUT", Method::Put),
+			("DELETE", Method::Delete),
+			("CONNECT", Method::Connect),
+			("OPTIONS", Method::Options),
+			("TRACE", Method::Trace),
+			("PATCH", Method::Patch),
+			("BREW", Method::Unknown("BREW".into()))
+		];
+
+		for (name, method) in methods {
+			assert_eq!(method, Method::from(name));
+			assert_eq!(name, method.to_string());
+		}
+	}
+
+	#[test]
+	fn test_method_case_insensitive() {
+		let methods = [
+			("get", Method::Get),
+			("Post", Method::Post),
+			("hEaD", Method::Head),
+			("put", Method::Put),
+			("Delete", Method::Delete),
+			("connect", Method::Connect),
+			("OpTiOnS", Method::Options),
+			("trace", Method::Trace),
+			("pAtCh", Method::Patch)
+		];
+
+		for (name, method) in methods {
+			assert_eq!(method, Method::from(name));
+			assert_eq!(Ok(method), name.parse::<Method>());
+		}
+
+		assert_eq!(Method::Unknown("brew".into()), Method::from("brew"));
+		assert_eq!(Err(ParseError::UnknownMethod), "brew".parse::<Method>());
+		assert_eq!(Err(ParseError::UnknownMethod), "".parse::<Method>());
+	}
+
+	#[test]
+	fn test_version_into() {
+		let m: Version = "HTTP/1.1".into();
+		let m2: Version = "HTTP/2.0".into();
+		let m3: Version = "321dshaui".into();
+		assert_eq!(m, Version::V1_1);
+		assert_eq!(m2, Version::V2_0);
+		assert_eq!(m3, Version::Uninitialized);
+	}
+
+	#[test]
+	fn test_version_round_trip() {
+		for version in [Version::V1_0, Version::V1_1, Version::V2_0, Version::V3_0] {
+			assert_eq!(version, Version::from(version.to_string().as_str()));
+		}
+		assert_eq!("HTTP/1.0", Version::V1_0.to_string());
+		assert_eq!("HTTP/3.0", Version::V3_0.to_string());
+		assert_eq!("", Version::from("HTTP/9.9").to_string());
+
+		let req: HttpRequest = String::from("GET / HTTP/1.0\r\n\r\n").into();
+		assert_eq!(Version::V1_0, req.version);
+		assert!(!req.keep_alive());
+	}
+
+	#[test]
+	fn test_read_http() {
+		let s: String = String::from("GET /greeting HTTP/1.1\r\nHost: localhost:3000\r\nUser-Agent: curl/7.64.1\r\nAccept: */*\r\n\r\ntestbody123");
+		let mut headers_expected = HashMap::new();
+		headers_expected.insert("Host".into(), "localhost:3000".into());
+		headers_expected.insert("Accept".into(), "*/*".into());
+		headers_expected.insert("User-Agent".into(), "curl/7.64.1".into());
+		let req: HttpRequest = s.into();
+		assert_eq!(Method::Get, req.method);
+		assert_eq!(Version::V1_1, req.version);
+		assert_eq!(Resource::Path("/greeting".to_string()), req.resource);
+		assert_eq!(headers_expected, req.headers);
+		assert_eq!("testbody123", req.msg_body);
+	}
+
+	#[test]
+	fn test_parse_one_pipelined() {
+		let first = "POST /first HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+		let second = "GET /second HTTP/1.1\r\nHost: localhost\r\n\r\n";
+		let buf = format!("{}{}", first, second);
+
+		let (req, used) = parse_one(buf.as_bytes()).unwrap();
+		assert_eq!(first.len(), used);
+		assert_eq!(Method::Post, req.method);
+		assert_eq!("hello", req.msg_body);
+
+		let (req, used) = parse_one(&buf.as_bytes()[used..]).unwrap();
+		assert_eq!(second.len(), used);
+		assert_eq!(Method::Get, req.method);
+		assert_eq!(Resource::Path("/second".to_string()), req.resource);
+	}
+
+	#[test]
+	fn test_parse_one_incomplete() {
+		let partial = b"POST /first HTTP/1.1\r\nContent-Length: 5\r\n\r\nhel";
+		assert_eq!(Err(ParseError::Incomplete), parse_one(partial).map(|_| ()));
+		assert_eq!(
+			Err(ParseError::Incomplete),
+			parse_one(b"GET / HTTP/1.1\r\n").map(|_| ())
+		);
+	}
+
+	#[test]
+	fn test_parse_one_content_length_overflow() {
+		let raw = format!(
+			"POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\nhi",
+			usize::MAX
+		);
+		assert_eq!(
+			Err(ParseError::InvalidContentLength),
+			parse_one(raw.as_bytes()).map(|_| ())
+		);
+	}
+
+	#[test]
+	fn test_parse_one_malformed_request_line() {
+		let parse = |raw: &[u8]| parse_one(raw).map(|_| ());
+
+		assert_eq!(
+			Err(ParseError::MalformedRequestLine),
+			parse(b"garbage\r\n\r\n")
+		);
+		assert_eq!(Err(ParseError::MissingVersion), parse(b"GET /\r\n\r\n"));
+		assert_eq!(Err(ParseError::EmptyRequest), parse(b"\r\n\r\n"));
+		assert_eq!(
+			Err(ParseError::MalformedRequestLine),
+			parse(b"GET  / HTTP/1.1\r\n\r\n")
+		);
+	}
+
+	#[test]
+	fn test_connection_has_token() {
+		let req: HttpRequest = String::from(
+			"GET /chat HTTP/1.1\r\nconnection: Upgrade, keep-alive\r\nUpgrade: websocket\r\n\r\n"
+		)
+		.into();
+		assert!(req.connection_has_token("upgrade"));
+		assert!(req.connection_has_token("keep-alive"));
+		assert!(req.connection_has_token("Keep-Alive"));
+		assert!(!req.connection_has_token("close"));
+		assert!(req.keep_alive());
+		assert!(req.is_websocket_upgrade());
+
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\nConnection: close\r\n\r\n").into();
+		assert!(!req.keep_alive());
+		assert!(!req.is_websocket_upgrade());
+	}
+
+	#[test]
+	fn test_invalid_percent_encoding() {
+		assert!(Resource::Path("/a%2".into()).has_invalid_percent_encoding());
+		assert!(Resource::Path("/a%zz".into()).has_invalid_percent_encoding());
+		assert!(Resource::Path("/a%".into()).has_invalid_percent_encoding());
+		assert!(!Resource::Path("/a%20".into()).has_invalid_percent_encoding());
+		assert!(!Resource::Path("/plain".into()).has_invalid_percent_encoding());
+	}
+
+	#[test]
+	fn test_canonical_string() {
+		let req: HttpRequest = String::from(
+			"POST /hooks/deploy HTTP/1.1\r\nX-Timestamp:  1700000000 \r\nContent-Type: application/json\r\nUser-Agent: rust\r\n\r\nabc"
+		)
+		.into();
+
+		assert_eq!(
+			"POST\n/hooks/deploy\ncontent-type:application/json\nx-signature:\nx-timestamp:1700000000\nba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+			req.canonical_string(&["X-Timestamp", "Content-Type", "X-Signature"])
+		);
+	}
+
+	#[test]
+	fn test_range() {
+		let range_of = |value: &str| {
+			let req: HttpRequest = format!("GET /video HTTP/1.1\r\nRange: {}\r\n\r\n", value).into();
+			req.range()
+		};
+
+		assert_eq!(Some(ByteRange::Suffix(500)), range_of("bytes=-500"));
+		assert_eq!(Some(ByteRange::From(500)), range_of("bytes=500-"));
+		assert_eq!(Some(ByteRange::FromTo(0, 99)), range_of("bytes=0-99"));
+		assert_eq!(None, range_of("bytes=99-0"));
+		assert_eq!(None, range_of("bytes=0-1, 5-6"));
+		assert_eq!(None, range_of("items=0-1"));
+
+		assert_eq!(Some((500, 1000)), ByteRange::Suffix(500).resolve(1000));
+		assert_eq!(Some((500, 1000)), ByteRange::From(500).resolve(1000));
+		assert_eq!(Some((0, 100)), ByteRange::FromTo(0, 99).resolve(1000));
+		assert_eq!(
+			Some((990, 1000)),
+			ByteRange::FromTo(990, 5000).resolve(1000)
+		);
+		assert_eq!(None, ByteRange::Suffix(0).resolve(1000));
+
+		let to_max = range_of(&format!("bytes=0-{}", u64::MAX)).unwrap();
+		assert_eq!(Some((0, 1000)), to_max.resolve(1000));
+	}
+
+	#[test]
+	fn test_parse_one_uri_too_long() {
+		let options = ParseOptions {
+			max_uri_bytes: 16,
+			..Default::default()
+		};
+		let request = |target: &str| format!("GET {} HTTP/1.1\r\n\r\n", target);
+
+		let under = request("/123456789012345");
+		assert!(parse_one_with(under.as_bytes(), &options).is_ok());
+
+		let over = request("/1234567890123456");
+		assert_eq!(
+			Err(ParseError::UriTooLong),
+			parse_one_with(over.as_bytes(), &options).map(|_| ())
+		);
+		assert_eq!(
+			Err(ParseError::UriTooLong),
+			parse_one_with(b"GET /1234567890123456", &options).map(|_| ())
+		);
+		assert_eq!(
+			Err(ParseError::UriTooLong),
+			parse_one_with(b"GET  \t/1234567890123456", &options).map(|_| ())
+		);
+	}
+
+	#[test]
+	fn test_resource_with_query() {
+		assert_eq!(
+			"/files?name=my%20file.txt&dir=%2Ftmp",
+			Resource::with_query("/files", &[("name", "my file.txt"), ("dir", "/tmp")])
+		);
+		assert_eq!("/files", Resource::with_query("/files", &[]));
+	}
+
+	#[test]
+	fn test_parse_stats_remaining() {
+		let first = "GET /first HTTP/1.1\r\n\r\n";
+		let second = "GET /second HTTP/1.1\r\n\r\n";
+		let buf = format!("{}{}", first, second);
+		let options = ParseOptions::default();
+
+		let (_, stats) = parse_one_with(buf.as_bytes(), &options).unwrap();
+		assert_eq!(first.len(), stats.consumed);
+		assert_eq!(second.len(), stats.bytes_remaining);
+
+		let (_, stats) = parse_one_with(&buf.as_bytes()[stats.consumed..], &options).unwrap();
+		assert_eq!(0, stats.bytes_remaining);
+	}
+
+	#[test]
+	fn test_cache_control() {
+		let req: HttpRequest =
+			String::from("GET / HTTP/1.1\r\nCache-Control: No-Cache, max-age=0, no-store\r\n\r\n").into();
+		let directives = req.cache_control();
+
+		assert_eq!(3, directives.len());
+		assert_eq!(Some(&None), directives.get("no-cache"));
+		assert_eq!(Some(&Some("0".to_string())), directives.get("max-age"));
+		assert_eq!(Some(&None), directives.get("no-store"));
+
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\n\r\n").into();
+		assert!(req.cache_control().is_empty());
+	}
+
+	#[test]
+	fn test_effective_method() {
+		let req: HttpRequest =
+			String::from("POST /items/1 HTTP/1.1\r\nX-HTTP-Method-Override: PATCH\r\n\r\n").into();
+		assert_eq!(Method::Patch, req.effective_method());
+
+		let req: HttpRequest =
+			String::from("POST /items/1 HTTP/1.1\r\nX-HTTP-Method-Override: PUT\r\n\r\n").into();
+		assert_eq!(Method::Put, req.effective_method());
+
+		let req: HttpRequest =
+			String::from("GET /items/1 HTTP/1.1\r\nX-HTTP-Method-Override: PATCH\r\n\r\n").into();
+		assert_eq!(Method::Get, req.effective_method());
+
+		let req: HttpRequest =
+			String::from("POST /items/1 HTTP/1.1\r\nX-HTTP-Method-Override: BREW\r\n\r\n").into();
+		assert_eq!(Method::Post, req.effective_method());
+
+		let req: HttpRequest = String::from("POST /items HTTP/1.1\r\n\r\n").into();
+		assert_eq!(Method::Post, req.effective_method());
+	}
+
+	#[test]
+	fn test_parse_one_smuggling_risk() {
+		let te_and_cl =
+			b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 4\r\n\r\n0\r\n\r\n";
+		assert_eq!(
+			Err(ParseError::SmugglingRisk),
+			parse_one(te_and_cl).map(|_| ())
+		);
+
+		let chunked_not_last = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n0\r\n\r\n";
+		assert_eq!(
+			Err(ParseError::SmugglingRisk),
+			parse_one(chunked_not_last).map(|_| ())
+		);
+	}
+
+	#[test]
+	fn test_parse_one_repeated_framing_headers() {
+		let parse = |headers: &str| {
+			let raw = format!("POST / HTTP/1.1\r\n{}\r\nhello", headers);
+			parse_one(raw.as_bytes()).map(|(req, _)| req.msg_body)
+		};
+
+		assert_eq!(
+			Err(ParseError::InvalidContentLength),
+			parse("Content-Length: 0\r\ncontent-length: 5\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::SmugglingRisk),
+			parse("Transfer-Encoding: chunked\r\ntransfer-encoding: identity\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::SmugglingRisk),
+			parse("Host: a\r\nhost: b\r\nContent-Length: 5\r\n")
+		);
+		assert_eq!(
+			Ok("hello".to_string()),
+			parse("Content-Length: 5\r\ncontent-length: 5\r\n")
+		);
+	}
+
+	#[test]
+	fn test_digest_auth() {
+		let req: HttpRequest = String::from(
+			"GET /dir/index.html HTTP/1.1\r\nAuthorization: Digest username=\"Mufasa\", realm=\"testrealm@host.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", uri=\"/dir/index.html?a=1,2\", qop=auth, nc=00000001, cnonce=\"0a4f113b\", response=\"6629fae49393a05397450978507c4ef1\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"\r\n\r\n"
+		)
+		.into();
+		let auth = req.digest_auth().unwrap();
+
+		assert_eq!("Mufasa", auth.username);
+		assert_eq!("testrealm@host.com", auth.realm);
+		assert_eq!("dcd98b7102dd2f0e8b11d0f600bfb0c093", auth.nonce);
+		assert_eq!("/dir/index.html?a=1,2", auth.uri);
+		assert_eq!("6629fae49393a05397450978507c4ef1", auth.response);
+		assert_eq!(Some("auth".to_string()), auth.qop);
+		assert_eq!(Some("00000001".to_string()), auth.nc);
+		assert_eq!(Some("0a4f113b".to_string()), auth.cnonce);
+		assert_eq!(None, auth.algorithm);
+
+		let req: HttpRequest =
+			String::from("GET / HTTP/1.1\r\nAuthorization: Basic QWxhZGRpbjpvcGVu\r\n\r\n").into();
+		assert_eq!(None, req.digest_auth());
+	}
+
+	#[test]
+	fn test_content_type_base() {
+		let content_type = |value: &str| {
+			let req: HttpRequest = format!("POST / HTTP/1.1\r\nContent-Type: {}\r\n\r\n", value).into();
+			req.content_type_base()
+		};
+
+		assert_eq!(
+			Some("application/json".to_string()),
+			content_type("Application/JSON; charset=utf-8")
+		);
+		assert_eq!(Some("text/plain".to_string()), content_type("text/plain"));
+
+		let req: HttpRequest = String::from("POST / HTTP/1.1\r\n\r\n").into();
+		assert_eq!(None, req.content_type_base());
+	}
+
+	#[test]
+	fn test_is_h2_preface() {
+		assert!(is_h2_preface(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"));
+		assert!(is_h2_preface(
+			b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00\x12\x04"
+		));
+		assert!(!is_h2_preface(b"PRI * HTTP/2.0\r\n\r\nSM\r\n"));
+		assert!(!is_h2_preface(b"PRI * HTTP/1.1\r\n\r\nSM\r\n\r\n"));
+		assert!(!is_h2_preface(b"GET / HTTP/1.1\r\n\r\n"));
+	}
+
+	#[test]
+	fn test_resource_strip_prefix() {
+		let path = |p: &str| Resource::Path(p.to_string());
+
+		assert_eq!(
+			Some(path("/users/1")),
+			path("/api/users/1").strip_prefix("/api")
+		);
+		assert_eq!(
+			Some(path("/users/1")),
+			path("/api/users/1").strip_prefix("/api/")
+		);
+		assert_eq!(Some(path("/?q=1")), path("/api?q=1").strip_prefix("/api"));
+		assert_eq!(Some(path("/")), path("/api").strip_prefix("/api"));
+		assert_eq!(None, path("/apix/users").strip_prefix("/api"));
+		assert_eq!(None, path("/other").strip_prefix("/api"));
+	}
+
+	#[test]
+	fn test_if_unmodified_since() {
+		let since = |value: &str| {
+			let req: HttpRequest = format!(
+				"PUT /doc HTTP/1.1\r\nIf-Unmodified-Since: {}\r\n\r\n",
+				value
+			)
+			.into();
+			req.if_unmodified_since()
+		};
+
+		assert_eq!(
+			Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1445412480)),
+			since("Wed, 21 Oct 2015 07:28:00 GMT")
+		);
+		assert_eq!(None, since("not a date"));
+	}
+
+	#[test]
+	fn test_sorted_headers() {
+		let req: HttpRequest = String::from(
+			"GET / HTTP/1.1\r\nuser-agent: rust\r\nAccept: */*\r\nHost: localhost\r\ncookie: a=1\r\n\r\n"
+		)
+		.into();
+		let names: Vec<String> = req.sorted_headers().into_iter().map(|(k, _)| k).collect();
+
+		assert_eq!(vec!["Accept", "cookie", "Host", "user-agent"], names);
+	}
+
+	#[test]
+	fn test_parse_weighted_list() {
+		assert_eq!(
+			vec![
+				("text/html".to_string(), 1.0),
+				("application/json".to_string(), 0.5),
+				("*/*".to_string(), 0.1)
+			],
+			parse_weighted_list("text/html, application/json;q=0.5, */*; q=0.1")
+		);
+		assert_eq!(
+			vec![("en".to_string(), 1.0), ("fr".to_string(), 0.0)],
+			parse_weighted_list("en;q=7, fr;q=-1")
+		);
+		assert_eq!(
+			vec![("de".to_string(), 1.0)],
+			parse_weighted_list("de, , es;q=high")
+		);
+	}
+
+	#[test]
+	fn test_form() {
+		let req: HttpRequest = String::from(
+			"GET /search HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\na=1&b=two+words&c="
+		)
+		.into();
+
+		assert_eq!(
+			vec![
+				("a".to_string(), "1".to_string()),
+				("b".to_string(), "two words".to_string()),
+				("c".to_string(), "".to_string())
+			],
+			req.form()
+		);
+	}
+
+	#[test]
+	fn test_form_charset() {
+		let req: HttpRequest = String::from(
+			"POST /form HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded; charset=ISO-8859-1\r\n\r\nname=Ren%E9e"
+		)
+		.into();
+		assert_eq!(vec![("name".to_string(), "Renée".to_string())], req.form());
+
+		let req: HttpRequest = String::from(
+			"POST /form HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\n_charset_=latin1&name=Ren%E9e"
+		)
+		.into();
+		assert_eq!("Renée", req.form()[1].1);
+
+		let req: HttpRequest = String::from("POST /form HTTP/1.1\r\n\r\nname=Ren%C3%A9e").into();
+		assert_eq!("Renée", req.form()[0].1);
+	}
+
+	#[test]
+	fn test_is_xhr() {
+		let req: HttpRequest =
+			String::from("GET / HTTP/1.1\r\nx-requested-with: xmlhttprequest\r\n\r\n").into();
+		assert!(req.is_xhr());
+
+		let req: HttpRequest =
+			String::from("GET / HTTP/1.1\r\nX-Requested-With: com.example.app\r\n\r\n").into();
+		assert!(!req.is_xhr());
+
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\n\r\n").into();
+		assert!(!req.is_xhr());
+	}
+
+	#[test]
+	fn test_header_value_with_colons() {
+		let req: HttpRequest = String::from(
+			"GET / HTTP/1.1\r\nHost: localhost:3000\r\nReferer: http://example.com:8080/a\r\nX-Empty:\r\n\r\n"
+		)
+		.into();
+
+		assert_eq!("localhost:3000", req.headers["Host"]);
+		assert_eq!("http://example.com:8080/a", req.headers["Referer"]);
+		assert_eq!("", req.headers["X-Empty"]);
+	}
+
+	#[test]
+	fn test_try_from_malformed_request_line() {
+		let parse = |raw: &str| HttpRequest::try_from(raw).map(|_| ());
+
+		assert_eq!(Err(ParseError::EmptyRequest), parse(""));
+		assert_eq!(Err(ParseError::EmptyRequest), parse("\r\nHost: a\r\n\r\n"));
+		assert_eq!(Err(ParseError::MalformedRequestLine), parse("GET\r\n\r\n"));
+		assert_eq!(Err(ParseError::MissingVersion), parse("GET /\r\n\r\n"));
+		assert_eq!(
+			Err(ParseError::MalformedRequestLine),
+			parse("GET / HTTP/1.1 extra\r\n\r\n")
+		);
+
+		let req = HttpRequest::parse_strict("POST /form HTTP/1.1\r\nHost: a\r\n\r\nbody").unwrap();
+		assert_eq!(Method::Post, req.method);
+		assert_eq!(Resource::Path("/form".into()), req.resource);
+		assert_eq!("a", req.headers["Host"]);
+		assert_eq!("body", req.msg_body);
+	}
+
+	#[test]
+	fn test_from_malformed_request_line_does_not_panic() {
+		let req: HttpRequest = String::from("GET HTTP/1.1\r\nHost: a\r\n\r\n").into();
+
+		assert_eq!(Method::Unknown("".into()), req.method);
+		assert_eq!("a", req.headers["Host"]);
+	}
+
+	#[test]
+	fn test_header_case_insensitive() {
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\nACCEPT: */*\r\n\r\n").into();
+
+		assert_eq!(Some("*/*"), req.header("accept"));
+		assert_eq!(Some("*/*"), req.header("Accept"));
+		assert_eq!(None, req.header("Accept-Language"));
+
+		for _ in 0..8 {
+			let req: HttpRequest = String::from("GET / HTTP/1.1\r\nHost: a\r\nhost: b\r\n\r\n").into();
+			assert_eq!(Some("b"), req.header("HOST"));
+		}
+	}
+
+	#[test]
+	fn test_is_retryable() {
+		let get: HttpRequest = String::from("GET / HTTP/1.1\r\n\r\n").into();
+		let post: HttpRequest = String::from("POST /pay HTTP/1.1\r\n\r\n").into();
+		let keyed: HttpRequest =
+			String::from("POST /pay HTTP/1.1\r\nIdempotency-Key: 8e03978e\r\n\r\n").into();
+
+		assert!(get.is_retryable());
+		assert!(!post.is_retryable());
+		assert!(keyed.is_retryable());
+		assert!(!Method::Patch.is_idempotent());
+	}
+
+	#[test]
+	fn test_header_all() {
+		let req: HttpRequest = String::from(
+			"GET / HTTP/1.1\r\nX-Forwarded-For: 10.0.0.1\r\nHost: a\r\nx-forwarded-for: 10.0.0.2\r\n\r\n"
+		)
+		.into();
+
+		assert_eq!(
+			vec!["10.0.0.1", "10.0.0.2"],
+			req.header_all("X-Forwarded-For")
+		);
+		assert_eq!(vec!["a"], req.header_all("host"));
+		assert!(req.header_all("Cookie").is_empty());
+		assert_eq!(3, req.header_list.len());
+	}
+
+	#[test]
+	fn test_query() {
+		let req: HttpRequest =
+			String::from("GET /search?q=&flag&tag=a&tag=b&name=J%C3%BCrgen HTTP/1.1\r\n\r\n").into();
+		let query = req.query();
+
+		assert_eq!("/search", req.path());
+		assert_eq!("", query["q"]);
+		assert_eq!("", query["flag"]);
+		assert_eq!("b", query["tag"]);
+		assert_eq!("Jürgen", query["name"]);
+
+		let req: HttpRequest = String::from("GET /plain HTTP/1.1\r\n\r\n").into();
+		assert_eq!("/plain", req.path());
+		assert_eq!(None, req.resource.query());
+		assert!(req.query().is_empty());
+	}
+
+	#[test]
+	fn test_fragment() {
+		let res = Resource::Path("/page#top".into());
+		assert_eq!(Some("top"), res.fragment());
+		assert_eq!("/page", res.path());
+		assert_eq!(None, res.query());
+
+		let res = Resource::Path("/page?a=1#frag?b=2".into());
+		assert_eq!(Some("frag?b=2"), res.fragment());
+		assert_eq!("/page", res.path());
+		assert_eq!(Some("a=1"), res.query());
+
+		let res = Resource::Path("/page".into());
+		assert_eq!(None, res.fragment());
+		assert_eq!("/page", res.path());
+		assert_eq!(None, res.query());
+	}
+
+	#[test]
+	fn test_resolve_range_response() {
+		let request =
+			|headers: &str| -> HttpRequest { format!("GET /video HTTP/1.1\r\n{}\r\n", headers).into() };
+		let etag = "\"abc\"";
+
+		assert_eq!(
+			RangeDecision::Partial(100, 200),
+			resolve_range_response(
+				&request("Range: bytes=100-199\r\nIf-Range: \"abc\"\r\n"),
+				etag,
+				1000
+			)
+		);
+		assert_eq!(
+			RangeDecision::Full,
+			resolve_range_response(
+				&request("Range: bytes=100-199\r\nIf-Range: \"old\"\r\n"),
+				etag,
+				1000
+			)
+		);
+		assert_eq!(
+			RangeDecision::Full,
+			resolve_range_response(
+				&request("Range: bytes=100-199\r\nIf-Range: W/\"abc\"\r\n"),
+				"W/\"abc\"",
+				1000
+			)
+		);
+		assert_eq!(
+			RangeDecision::NotSatisfiable,
+			resolve_range_response(&request("Range: bytes=5000-\r\n"), etag, 1000)
+		);
+		assert_eq!(
+			RangeDecision::Full,
+			resolve_range_response(&request(""), etag, 1000)
+		);
+	}
+
+	#[test]
+	fn test_to_raw_bytes_round_trip() {
+		let raw = "POST /upload?id=3 HTTP/1.1\r\nHost: localhost:3000\r\nx-tag: a\r\nX-Tag: b\r\nContent-Length: 5\r\n\r\nhello";
+		let req: HttpRequest = raw.to_string().into();
+
+		assert_eq!(raw.as_bytes(), req.to_raw_bytes());
+	}
+
+	#[test]
+	fn test_body_keeps_line_breaks() {
+		let req: HttpRequest =
+			String::from("POST /notes HTTP/1.1\r\nHost: a\r\n\r\n{\r\n  \"a\": 1\n}\n").into();
+
+		assert_eq!("{\r\n  \"a\": 1\n}\n", req.msg_body);
+	}
+
+	#[test]
+	fn test_decoded_path() {
+		let decoded = |target: &str| -> String {
+			let req: HttpRequest = format!("GET {} HTTP/1.1\r\n\r\n", target).into();
+			req.decoded_path()
+		};
+
+		assert_eq!(
+			"/files/my document.txt",
+			decoded("/files/my%20document.txt")
+		);
+		assert_eq!("/a+b", decoded("/a+b?q=a+b"));
+		assert_eq!("/bad%2", decoded("/bad%2"));
+		assert_eq!("/bad%zz", decoded("/bad%zz"));
+		assert_eq!("/a/b", decoded("/a%2Fb"));
+		assert_eq!("/café", decoded("/caf%C3%A9"));
+	}
+
+	#[test]
+	fn test_target_forms() {
+		let request = |line: &str| -> HttpRequest { format!("{}\r\n\r\n", line).into() };
+
+		let req = request("GET /index.html HTTP/1.1");
+		assert_eq!(Resource::Path("/index.html".into()), req.resource);
+		assert_eq!(Ok(()), req.validate_target_form());
+
+		let req = request("GET http://example.com:8080/a/b?c=1#d HTTP/1.1");
+		assert_eq!(
+			Resource::Absolute("http://example.com:8080/a/b?c=1#d".into()),
+			req.resource
+		);
+		assert_eq!("/a/b", req.path());
+		assert_eq!(Some("c=1"), req.resource.query());
+		assert_eq!("/", request("GET http://example.com HTTP/1.1").path());
+		assert_eq!(Ok(()), req.validate_target_form());
+
+		let req = request("CONNECT example.com:443 HTTP/1.1");
+		assert_eq!(Resource::Authority("example.com:443".into()), req.resource);
+		assert_eq!(Ok(()), req.validate_target_form());
+
+		let req = request("OPTIONS * HTTP/1.1");
+		assert_eq!(Resource::Asterisk, req.resource);
+		assert_eq!("*", req.resource.as_str());
+		assert_eq!(Ok(()), req.validate_target_form());
+
+		for line in [
+			"CONNECT /tunnel HTTP/1.1",
+			"GET * HTTP/1.1",
+			"GET example.com:443 HTTP/1.1"
+		] {
+			assert_eq!(
+				Err(ParseError::InvalidTargetForm),
+				request(line).validate_target_form()
+			);
+		}
+	}
+
+	#[test]
+	fn test_cookies() {
+		let req: HttpRequest = String::from(
+			"GET / HTTP/1.1\r\ncookie: session=abc==;  theme = dark ;flag\r\nCookie: lang=en\r\n\r\n"
+		)
+		.into();
+		let cookies = req.cookies();
+
+		assert_eq!(3, cookies.len());
+		assert_eq!("abc==", cookies["session"]);
+		assert_eq!("dark", cookies["theme"]);
+		assert_eq!("en", cookies["lang"]);
+
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\n\r\n").into();
+		assert!(req.cookies().is_empty());
+	}
+
+	#[test]
+	fn test_body_without_bom() {
+		let with_bom: HttpRequest = String::from("POST / HTTP/1.1\r\n\r\n\u{feff}{\"a\":1}").into();
+		let without_bom: HttpRequest = String::from("POST / HTTP/1.1\r\n\r\n{\"a\":1}").into();
+
+		assert_eq!(&[0xef, 0xbb, 0xbf], &with_bom.msg_body.as_bytes()[..3]);
+		assert_eq!("{\"a\":1}", with_bom.body_without_bom());
+		assert_eq!("{\"a\":1}", without_bom.body_without_bom());
+	}
+
+	#[test]
+	fn test_preferred_charset() {
+		let req: HttpRequest =
+			String::from("GET / HTTP/1.1\r\nAccept-Charset: utf-8, iso-8859-1;q=0.5\r\n\r\n").into();
+
+		assert_eq!(
+			Some("UTF-8".to_string()),
+			req.preferred_charset(&["iso-8859-1", "UTF-8"])
+		);
+		assert_eq!(
+			Some("iso-8859-1".to_string()),
+			req.preferred_charset(&["us-ascii", "iso-8859-1"])
+		);
+		assert_eq!(None, req.preferred_charset(&["us-ascii", "utf-16"]));
+
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\n\r\n").into();
+		assert_eq!(
+			Some("us-ascii".to_string()),
+			req.preferred_charset(&["us-ascii", "utf-8"])
+		);
+		assert_eq!(None, req.preferred_charset(&[]));
+	}
+
+	#[test]
+	fn test_display_round_trip() {
+		let raw = "PUT /items/7?v=2 HTTP/1.0\r\nContent-Length: 7\r\nHost: localhost:3000\r\nX-Id: 1\r\n\r\n{\"a\":1}";
+		let req: HttpRequest = raw.to_string().into();
+
+		assert_eq!(raw, req.to_string());
+		assert_eq!(raw, String::from(req.clone()));
+
+		let reparsed: HttpRequest = req.to_string().into();
+		assert_eq!(req.to_string(), reparsed.to_string());
+	}
+
+	#[test]
+	fn test_display_uninitialized() {
+		use std::fmt::Write;
+
+		for raw in [
+			"garbage\r\n\r\n",
+			"GET /\r\n\r\n",
+			"GET / HTTP/9\r\n\r\n",
+			""
+		] {
+			let req: HttpRequest = raw.to_string().into();
+			assert!(write!(String::new(), "{}", req).is_err(), "{:?}", raw);
+			assert_eq!("", String::from(req));
+		}
+	}
+
+	#[test]
+	fn test_request_line_trailing_whitespace() {
+		for line in [
+			"GET /path HTTP/1.1   ",
+			" GET /path HTTP/1.1",
+			"GET  /path HTTP/1.1",
+			"GET\t/path HTTP/1.1"
+		] {
+			let raw = format!("{}\r\nHost: a\r\n\r\n", line);
+
+			let lenient: HttpRequest = raw.clone().into();
+			assert_eq!(Method::Get, lenient.method);
+			assert_eq!(Resource::Path("/path".into()), lenient.resource);
+			assert_eq!(Version::V1_1, lenient.version);
+
+			assert_eq!(
+				Err(ParseError::MalformedRequestLine),
+				HttpRequest::try_from(raw.as_str()).map(|_| ())
+			);
+		}
+	}
+
+	#[test]
+	fn test_accepts_encoding() {
+		let request =
+			|headers: &str| -> HttpRequest { format!("GET / HTTP/1.1\r\n{}\r\n", headers).into() };
+
+		let req = request("Accept-Encoding: deflate, GZIP;q=0.5\r\n");
+		assert!(req.accepts_encoding("gzip"));
+		assert!(!req.accepts_encoding("br"));
+		assert!(req.accepts_encoding("identity"));
+
+		let req = request("Accept-Encoding: *, gzip;q=0\r\n");
+		assert!(!req.accepts_encoding("gzip"));
+		assert!(req.accepts_encoding("br"));
+
+		let req = request("");
+		assert!(!req.accepts_encoding("gzip"));
+		assert!(req.accepts_encoding("identity"));
+	}
+
+	#[test]
+	fn test_chunked_body() {
+		let req: HttpRequest = String::from(
+			"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5;name=value\r\nhello\r\n7\r\n, world\r\n0\r\nExpires: never\r\n\r\n"
+		)
+		.into();
+		assert_eq!("hello, world", req.msg_body);
+
+		let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\na\r\n0123456789\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n";
+		let (req, stats) = parse_one_with(raw, &ParseOptions::default()).unwrap();
+		assert_eq!("0123456789", req.msg_body);
+		assert_eq!(raw.len() - 18, stats.consumed);
+		assert_eq!(18, stats.bytes_remaining);
+	}
+
+	#[test]
+	fn test_chunked_body_errors() {
+		let parse = |body: &[u8]| {
+			let mut raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+			raw.extend_from_slice(body);
+			parse_one(&raw).map(|(req, _)| req.msg_body)
+		};
+
+		assert_eq!(Err(ParseError::Incomplete), parse(b"5\r\nhel"));
+		assert_eq!(Err(ParseError::Incomplete), parse(b"5\r\nhello\r\n0\r\n"));
+		assert_eq!(
+			Err(ParseError::InvalidChunk),
+			parse(b"zz\r\nhello\r\n0\r\n\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::InvalidChunk),
+			parse(b"2\r\nhello\r\n0\r\n\r\n")
+		);
+		assert_eq!(Ok(String::new()), parse(b"0\r\n\r\n"));
+		assert_eq!(
+			Err(ParseError::InvalidChunk),
+			parse(b"+5\r\nhello\r\n0\r\n\r\n")
+		);
+		assert_eq!(Err(ParseError::InvalidChunk), parse(b"-0\r\n\r\n"));
+	}
+
+	#[test]
+	fn test_chunked_body_limits() {
+		let options = ParseOptions {
+			max_header_bytes: 64,
+			max_chunks: 3,
+			..Default::default()
+		};
+		let parse = |body: &[u8]| {
+			let mut raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+			raw.extend_from_slice(body);
+			parse_one_with(&raw, &options).map(|(req, _)| req.msg_body)
+		};
+
+		assert_eq!(
+			Ok("ab".to_string()),
+			parse(b"1\r\na\r\n1\r\nb\r\n0\r\n\r\n")
+		);
+		assert_eq!(
+			Err(ParseError::TooManyChunks),
+			parse(b"1\r\na\r\n1\r\nb\r\n1\r\nc\r\n0\r\n\r\n")
+		);
+
+		let long_ext = format!("1;{}", "x".repeat(64));
+		assert_eq!(Err(ParseError::HeadersTooLarge), parse(long_ext.as_bytes()));
+		assert_eq!(
+			Err(ParseError::HeadersTooLarge),
+			parse(format!("{}\r\na\r\n0\r\n\r\n", long_ext).as_bytes())
+		);
+
+		let trailers = "X-T: 0123456789\r\n".repeat(5);
+		assert_eq!(
+			Err(ParseError::HeadersTooLarge),
+			parse(format!("0\r\n{}", trailers).as_bytes())
+		);
+
+		let head = format!("GET / HTTP/1.1\r\nX-Pad: {}\r\n", "p".repeat(64));
+		assert_eq!(
+			Err(ParseError::HeadersTooLarge),
+			parse_one_with(head.as_bytes(), &options).map(|_| ())
+		);
+	}
+
+	#[test]
+	fn test_parse_one_content_length_syntax() {
+		let parse = |headers: &str| {
+			let raw = format!("POST / HTTP/1.1\r\n{}\r\nhello", headers);
+			parse_one(raw.as_bytes()).map(|(req, _)| req.msg_body)
+		};
+
+		assert_eq!(Ok("hello".to_string()), parse("Content-Length:  5 \r\n"));
+		for len in ["+5", "-5", "5,5", "0x5", ""] {
+			assert_eq!(
+				Err(ParseError::InvalidContentLength),
+				parse(&format!("Content-Length: {}\r\n", len))
+			);
+		}
+		assert_eq!(
+			Err(ParseError::InvalidContentLength),
+			parse("Content-Length: 0\r\nContent-Length: 5\r\n")
+		);
+	}
+
+	#[test]
+	fn test_as_parts() {
+		let req: HttpRequest = String::from("DELETE /items/7?force=1 HTTP/2.0\r\n\r\n").into();
+		let (method, path, version) = req.as_parts();
+
+		assert_eq!(&Method::Delete, method);
+		assert_eq!("/items/7", path);
+		assert_eq!(&Version::V2_0, version);
+	}
+
+	#[test]
+	fn test_request_ref() {
+		let buf = "GET /a?b=1 HTTP/1.1\r\nX-Id: 1\r\nx-id: 2\r\nHost: h\r\n\r\nbody\r\nmore";
+		let req = HttpRequestRef::from(buf);
+
+		assert_eq!(Method::Get, req.method);
+		assert_eq!("/a?b=1", req.resource);
+		assert_eq!(
+			vec![("X-Id", "1"), ("x-id", "2"), ("Host", "h")],
+			req.headers
+		);
+		assert_eq!(Some("2"), req.header("X-ID"));
+		assert_eq!("body\r\nmore", req.msg_body);
+
+		let owned = req.into_owned();
+		assert_eq!(Resource::Path("/a?b=1".into()), owned.resource);
+		assert_eq!(vec!["1", "2"], owned.header_all("x-id"));
+		assert_eq!(
+			("1", "2"),
+			(&*owned.headers["X-Id"], &*owned.headers["x-id"])
+		);
+		assert_eq!(buf, String::from_utf8(owned.to_raw_bytes()).unwrap());
+
+		let owned: HttpRequest = String::from("garbage").into();
+		assert_eq!(Resource::Path("".into()), owned.resource);
+	}
+
+	#[test]
+	fn test_client_ip() {
+		let trusted = ["10.0.0.1", "10.0.0.2"];
+		let req: HttpRequest = String::from(
+			"GET / HTTP/1.1\r\nX-Forwarded-For: 6.6.6.6, 1.2.3.4\r\nX-Forwarded-For: 10.0.0.2\r\n\r\n"
+		)
+		.into();
+
+		assert_eq!("1.2.3.4", req.client_ip(&trusted, "10.0.0.1"));
+		assert_eq!("9.9.9.9", req.client_ip(&trusted, "9.9.9.9"));
+
+		let req: HttpRequest =
+			String::from("GET / HTTP/1.1\r\nX-Forwarded-For: 10.0.0.2\r\n\r\n").into();
+		assert_eq!("10.0.0.1", req.client_ip(&trusted, "10.0.0.1"));
+
+		let req: HttpRequest = String::from("GET / HTTP/1.1\r\n\r\n").into();
+		assert_eq!("10.0.0.1", req.client_ip(&trusted, "10.0.0.1"));
 	}
 }
@@ -1,6 +1,16 @@
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
-use std::fmt::Display;
-use std::io::{Result, Write};
+use std::fmt::{Debug, Display};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Result, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::cookie::{Cookie, CookieError, CookieOptions};
+use crate::date::format_clf;
+use crate::request::HttpRequest;
+use crate::url::encode_pairs;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct HttpResponse<'a> {
@@ -8,170 +18,2211 @@ pub struct HttpResponse<'a> {
 	pub status_code: &'a str,
 	pub status_text: &'a str,
 	pub headers: Option<HashMap<&'a str, String>>,
-	pub body: Option<String>
+	pub body: Option<String>,
+	/// Binary or streamed body, sent instead of `body` when set. See
+	/// `HttpResponse::with_bytes` and `HttpResponse::set_body_reader`.
+	pub body_source: Option<BodySource<'a>>,
+	/// Sent as one `Set-Cookie` header each, see `HttpResponse::add_cookie`.
+	pub cookies: Vec<Cookie>
+}
+
+/// How the body of a response is delimited on the wire, see `HttpResponse::body_framing`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Framing {
+	/// Exactly this many bytes follow the head.
+	Length(usize),
+	/// The body uses chunked transfer coding.
+	Chunked,
+	/// The response can't have a body.
+	None,
+	/// The body runs until the connection closes.
+	UntilClose
+}
+
+/// Error returned when a response shouldn't be sent as built.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SerializeError {
+	/// More headers than the configured maximum.
+	TooManyHeaders,
+	/// The header block is larger than the configured maximum, in bytes.
+	HeadersTooLarge,
+	/// These headers have values with control or non-ASCII characters.
+	InvalidHeaderValues(Vec<String>),
+	/// A status code outside `100..=599`.
+	InvalidStatus(u16),
+	/// A reason phrase with control characters, which would break the status line.
+	InvalidReason
+}
+
+impl Display for SerializeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::TooManyHeaders => f.write_str("too many response headers"),
+			Self::HeadersTooLarge => f.write_str("response headers are too large"),
+			Self::InvalidHeaderValues(names) => {
+				write!(f, "invalid response header values: {}", names.join(", "))
+			}
+			Self::InvalidStatus(code) => write!(f, "invalid response status code: {}", code),
+			Self::InvalidReason => f.write_str("invalid response reason phrase")
+		}
+	}
+}
+
+impl std::error::Error for SerializeError {}
+
+/// Binary body, or one streamed from a reader of known length. Binary bodies
+/// convert from a `Vec<u8>`, readers are set with `HttpResponse::set_body_reader`.
+#[derive(Clone)]
+pub struct BodySource<'a>(Source<'a>);
+
+#[derive(Clone)]
+enum Source<'a> {
+	Bytes(Vec<u8>),
+	/// Shared so responses stay `Clone`. Clones read from the same reader, so only
+	/// one of them can send the body.
+	Reader(Arc<Mutex<Box<dyn Read + Send + 'a>>>, usize)
+}
+
+impl<'a> BodySource<'a> {
+	/// The bytes of a binary body, `None` for a reader since they haven't been read.
+	pub fn as_bytes(&self) -> Option<&[u8]> {
+		match &self.0 {
+			Source::Bytes(bytes) => Some(bytes),
+			Source::Reader(..) => None
+		}
+	}
+
+	/// Length in bytes, as sent in `Content-Length`.
+	pub fn len(&self) -> usize {
+		match &self.0 {
+			Source::Bytes(bytes) => bytes.len(),
+			Source::Reader(_, len) => *len
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+impl<'a> From<Vec<u8>> for BodySource<'a> {
+	fn from(bytes: Vec<u8>) -> Self {
+		BodySource(Source::Bytes(bytes))
+	}
+}
+
+impl<'a> Debug for BodySource<'a> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.0 {
+			Source::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+			Source::Reader(_, len) => f
+				.debug_struct("Reader")
+				.field("len", len)
+				.finish_non_exhaustive()
+		}
+	}
+}
+
+/// A reader is only equal to its clones, since comparing contents would consume it.
+impl<'a> PartialEq for BodySource<'a> {
+	fn eq(&self, other: &Self) -> bool {
+		match (&self.0, &other.0) {
+			(Source::Bytes(a), Source::Bytes(b)) => a == b,
+			(Source::Reader(a, _), Source::Reader(b, _)) => Arc::ptr_eq(a, b),
+			_ => false
+		}
+	}
+}
+
+impl<'a> Default for HttpResponse<'a> {
+	fn default() -> Self {
+		Self {
+			version: "HTTP/1.1",
+			status_code: "200",
+			status_text: "OK",
+			headers: None,
+			body: None,
+			body_source: None,
+			cookies: Vec::new()
+		}
+	}
+}
+
+impl<'a> HttpResponse<'a> {
+	/// Deprecated. Use `HttpResponse::ok(...)`, `HttpResponse::bad_request(...)`, etc instead.
+	#[deprecated]
+	pub fn new(
+		status_code: &'a str,
+		headers: Option<HashMap<&'a str, String>>,
+		body: Option<String>
+	) -> HttpResponse<'a> {
+		let mut response: HttpResponse<'a> = HttpResponse::default();
+
+		if status_code != "200" {
+			response.status_code = status_code;
+		};
+
+		response.headers = match &headers {
+			Some(_h) => headers,
+			None => {
+				let mut h: HashMap<&str, String> = HashMap::new();
+				h.insert("Content-Type", "text/plain".to_string());
+				Some(h)
+			}
+		};
+
+		response.status_text = match response.status_code {
+			"200" => "OK",
+			"400" => "Bad Request",
+			"404" => "Not Found",
+			"500" => "Internal Server Error",
+			_ => ""
+		};
+
+		response.body = body;
+		response
+	}
+
+	/// Writes the whole response to `stream`, returning the number of bytes written.
+	/// A failed or short write is returned as an error, e.g. `BrokenPipe`.
+	pub fn send_response(&self, stream: &mut impl Write) -> Result<usize> {
+		match self.body_source.as_ref().map(|source| &source.0) {
+			Some(Source::Reader(reader, len)) => {
+				let head = self.head("\r\n", *len);
+				stream.write_all(head.as_bytes())?;
+				let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+				let sent = io::copy(&mut (&mut *reader).take(*len as u64), stream)?;
+
+				if sent < *len as u64 {
+					return Err(io::Error::new(
+						io::ErrorKind::UnexpectedEof,
+						"body reader ended before the declared length"
+					));
+				}
+
+				Ok(head.len() + len)
+			}
+			_ => {
+				let bytes = self.to_bytes();
+				stream.write_all(&bytes)?;
+				Ok(bytes.len())
+			}
+		}
+	}
+
+	/// Like `send_response`, but flushes `stream` after the head and after every body
+	/// chunk so a streaming client sees data as soon as it's written.
+	pub fn send_streaming(&self, stream: &mut impl Write) -> Result<()> {
+		stream.write_all(self.head("\r\n", self.body_len()).as_bytes())?;
+		stream.flush()?;
+
+		let Some(Source::Reader(reader, len)) = self.body_source.as_ref().map(|source| &source.0)
+		else {
+			stream.write_all(self.raw_body())?;
+			return stream.flush();
+		};
+
+		let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+		let mut remaining = *len;
+		let mut chunk = [0; 8192];
+		while remaining > 0 {
+			let n = reader.read(&mut chunk[..remaining.min(8192)])?;
+			if n == 0 {
+				return Err(io::Error::new(
+					io::ErrorKind::UnexpectedEof,
+					"body reader ended before the declared length"
+				));
+			}
+
+			stream.write_all(&chunk[..n])?;
+			stream.flush()?;
+			remaining -= n;
+		}
+
+		Ok(())
+	}
+
+	/// The status line and headers as sent, followed by a `[<n> bytes body omitted]`
+	/// marker instead of the body, for logging without leaking its content.
+	pub fn head_string(&self) -> String {
+		format!(
+			"{}[{} bytes body omitted]",
+			self.head("\r\n", self.body_len()),
+			self.body_len()
+		)
+	}
+
+	/// The header lines followed by the blank line that ends the head, leaving out the
+	/// status line and the body. `Content-Length` is the body's length unless the
+	/// caller set one, which is sent as is. Interim `1xx` responses get none.
+	pub fn headers_block(&self) -> String {
+		self.block("\r\n", self.body_len())
+	}
+
+	/// Serializes the response using `eol` as the line ending of the status line and
+	/// headers, keeping a text body verbatim. Meant for golden files normalized to `\n`;
+	/// the wire format always uses `\r\n`.
+	///
+	/// A binary body is converted lossily and a reader is left out, since it can only
+	/// be consumed once; `Content-Length` always matches the body in the string. Use
+	/// `into_bytes` or `send_response` to get the body as sent.
+	pub fn to_string_with_eol(&self, eol: &str) -> String {
+		let body = match self.body_source.as_ref().map(|source| &source.0) {
+			Some(Source::Bytes(bytes)) => String::from_utf8_lossy(bytes),
+			Some(Source::Reader(..)) => Cow::Borrowed(""),
+			None => Cow::Borrowed(self.body.as_deref().unwrap_or(""))
+		};
+
+		format!("{}{}", self.head(eol, body.len()), body)
+	}
+
+	/// Whether an explicitly set `Content-Length` header matches the body's byte length.
+	/// Responses without one are consistent, since the length is computed when sending.
+	pub fn content_length_consistent(&self) -> bool {
+		match self.header("Content-Length") {
+			Some(len) => len.trim().parse() == Ok(self.body_len()),
+			None => true
+		}
+	}
+
+	/// The `Accept-Ranges` header, e.g. `bytes` or `none` from an upstream response.
+	pub fn accept_ranges(&self) -> Option<&str> {
+		self.header("Accept-Ranges")
+	}
+
+	/// Advertises whether range requests are supported, e.g. `bytes` or `none`.
+	pub fn set_accept_ranges(&mut self, value: &str) {
+		self.set_header("Accept-Ranges", value.to_string());
+	}
+
+	/// Checks the response against header limits before sending it, guarding against
+	/// handlers that generate headers without bound. `max_total_bytes` applies to the
+	/// whole header block as returned by `headers_block`.
+	pub fn validate_limits(
+		&self,
+		max_headers: usize,
+		max_total_bytes: usize
+	) -> std::result::Result<(), SerializeError> {
+		if self.headers.as_ref().map_or(0, HashMap::len) + self.cookies.len() > max_headers {
+			return Err(SerializeError::TooManyHeaders);
+		}
+
+		if self.headers_block().len() > max_total_bytes {
+			return Err(SerializeError::HeadersTooLarge);
+		}
+
+		Ok(())
+	}
+
+	/// Checks that every header value, cookies included, only has visible ASCII,
+	/// spaces and tabs. A raw control character or non-ASCII byte makes some clients
+	/// reject the whole response; the offending header names are returned, sorted.
+	pub fn validate(&self) -> std::result::Result<(), SerializeError> {
+		let valid = |value: &str| {
+			value
+				.bytes()
+				.all(|b| b == b'\t' || (b' '..=b'~').contains(&b))
+		};
+
+		let mut invalid: Vec<String> = self
+			.headers
+			.iter()
+			.flatten()
+			.filter(|(_, v)| !valid(v))
+			.map(|(k, _)| k.to_string())
+			.collect();
+		if self
+			.cookies
+			.iter()
+			.any(|cookie| !valid(&cookie.to_string()))
+		{
+			invalid.push("Set-Cookie".to_string());
+		}
+
+		if invalid.is_empty() {
+			Ok(())
+		} else {
+			invalid.sort();
+			Err(SerializeError::InvalidHeaderValues(invalid))
+		}
+	}
+
+	/// Parses `Content-Range: bytes start-end/total` from a partial response into
+	/// inclusive `(start, end)` bounds and the total length, which is `None` when sent as `*`.
+	pub fn content_range(&self) -> Option<(u64, u64, Option<u64>)> {
+		let value = self.header("Content-Range")?.trim();
+		let (unit, range) = value.split_once(' ')?;
+		if !unit.eq_ignore_ascii_case("bytes") {
+			return None;
+		}
+
+		let (bounds, total) = range.trim().split_once('/')?;
+		let (start, end) = bounds.split_once('-')?;
+		let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+		let total = match total {
+			"*" => None,
+			total => Some(total.parse().ok()?)
+		};
+
+		(start <= end && total.is_none_or(|t| end < t)).then_some((start, end, total))
+	}
+
+	/// Replaces the body with `f` applied to it (or to `""` if there's none), leaving
+	/// the status and headers untouched. Handy for middleware such as minification.
+	///
+	/// Only text bodies are mapped: a binary or streamed `body_source` can't be passed
+	/// as a `String`, so the response is returned unchanged and `f` isn't called.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let response = HttpResponse::ok(None, Some("<p> hi </p>".into()));
+	/// let response = response.map_body(|b| b.replace(' ', ""));
+	/// assert_eq!(Some("<p>hi</p>".to_string()), response.body);
+	///
+	/// let png = HttpResponse::ok(None, None).with_bytes(vec![0x89, b'P', b'N', b'G']);
+	/// let png = png.map_body(|b| b.to_uppercase());
+	/// assert_eq!(Some(&[0x89, b'P', b'N', b'G'][..]), png.body_bytes());
+	/// ```
+	pub fn map_body(mut self, f: impl FnOnce(String) -> String) -> Self {
+		if self.body_source.is_none() {
+			self.body = Some(f(self.body.take().unwrap_or_default()));
+		}
+		self
+	}
+
+	/// How a reader of this response delimits its body, following RFC 7230 §3.3.3.
+	/// Responses to `HEAD` also have no body, which can't be told from the response alone.
+	pub fn body_framing(&self) -> Framing {
+		if self.status_code.starts_with('1') || matches!(self.status_code, "204" | "304") {
+			return Framing::None;
+		}
+
+		if let Some(te) = self.header("Transfer-Encoding") {
+			let last = te.rsplit(',').next().unwrap_or("").trim();
+			return if last.eq_ignore_ascii_case("chunked") {
+				Framing::Chunked
+			} else {
+				Framing::UntilClose
+			};
+		}
+
+		match self.header("Content-Length") {
+			Some(len) => len
+				.trim()
+				.parse()
+				.map_or(Framing::UntilClose, Framing::Length),
+			None => Framing::Length(self.body_len())
+		}
+	}
+
+	/// Collapses headers that may appear only once (`Content-Type`, `Content-Length`,
+	/// `Location`, `Content-Encoding`) but were set under differently-cased names,
+	/// which would otherwise be sent twice. The crate's constructors always use the
+	/// canonical spelling, so a differently-cased entry was set later and wins.
+	pub fn dedup_singleton_headers(&mut self) {
+		let Some(headers) = self.headers.as_mut() else {
+			return;
+		};
+
+		for name in [
+			"Content-Type",
+			"Content-Length",
+			"Location",
+			"Content-Encoding"
+		] {
+			let mut keys: Vec<&'a str> = headers
+				.keys()
+				.filter(|k| k.eq_ignore_ascii_case(name))
+				.copied()
+				.collect();
+			if keys.len() < 2 {
+				continue;
+			}
+
+			// Canonical spelling first, then the variants in a stable order.
+			keys.sort_by_key(|k| (*k != name, *k));
+			let value = headers.remove(keys.last().unwrap()).unwrap();
+			headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
+			headers.insert(name, value);
+		}
+	}
+
+	/// Sets a `Link` header with the `next`, `prev`, `first` and `last` pagination
+	/// relations, omitting the absent ones. Nothing is set if all are absent.
+	pub fn with_pagination_links(
+		&mut self,
+		next: Option<&str>,
+		prev: Option<&str>,
+		first: Option<&str>,
+		last: Option<&str>
+	) {
+		let links: Vec<String> = [
+			("next", next),
+			("prev", prev),
+			("first", first),
+			("last", last)
+		]
+		.into_iter()
+		.filter_map(|(rel, uri)| uri.map(|uri| format!("<{}>; rel=\"{}\"", uri, rel)))
+		.collect();
+
+		if !links.is_empty() {
+			self.set_header("Link", links.join(", "));
+		}
+	}
+
+	/// Sets `Server-Timing` from `(name, duration in ms, description)` metrics, e.g.
+	/// `db;dur=53.2;desc="query"`. Absent durations and descriptions are left out.
+	pub fn with_server_timing(&mut self, metrics: &[(&str, Option<f64>, Option<&str>)]) {
+		let entries: Vec<String> = metrics
+			.iter()
+			.map(|(name, dur, desc)| {
+				let mut entry = name.to_string();
+				if let Some(dur) = dur {
+					entry += &format!(";dur={}", dur);
+				}
+				if let Some(desc) = desc {
+					entry += &format!(";desc={}", quote(desc));
+				}
+				entry
+			})
+			.collect();
+
+		self.set_header("Server-Timing", entries.join(", "));
+	}
+
+	/// Adds a cookie, sent in its own `Set-Cookie` header so several can be set.
+	/// Cookies that would inject attributes or headers are refused, see
+	/// `Cookie::validate`.
+	/// ```
+	/// use http::cookie::{CookieError, CookieOptions};
+	/// use http::response::HttpResponse;
+	///
+	/// let mut response = HttpResponse::ok(None, None);
+	/// let options = CookieOptions { http_only: true, ..Default::default() };
+	/// response.add_cookie("session", "abc", options).unwrap();
+	/// assert!(response.to_string().contains("Set-Cookie:session=abc; HttpOnly\r\n"));
+	///
+	/// let refused = response.add_cookie("id", "1\r\nX-Injected: 1", CookieOptions::default());
+	/// assert_eq!(Err(CookieError::InvalidValue), refused);
+	/// ```
+	pub fn add_cookie(
+		&mut self,
+		name: &str,
+		value: &str,
+		options: CookieOptions
+	) -> std::result::Result<(), CookieError> {
+		let cookie = Cookie::with_options(name, value, options);
+		cookie.validate()?;
+		self.cookies.push(cookie);
+		Ok(())
+	}
+
+	/// Advertises alternative services such as `h3=":443"; ma=86400` in `Alt-Svc`.
+	/// An empty or blank value is ignored, since it isn't a valid header value.
+	pub fn with_alt_svc(mut self, value: &str) -> Self {
+		if !value.trim().is_empty() {
+			self.set_header("Alt-Svc", value.trim().to_string());
+		}
+		self
+	}
+
+	/// Sets `Clear-Site-Data`, e.g. on logout, with each directive quoted. Directives
+	/// other than `cache`, `cookies`, `storage`, `executionContexts` and `*` are
+	/// skipped, and no header is set if none is left.
+	pub fn with_clear_site_data(mut self, directives: &[&str]) -> Self {
+		const KNOWN: [&str; 5] = ["cache", "cookies", "storage", "executionContexts", "*"];
+
+		let directives: Vec<String> = directives
+			.iter()
+			.filter(|directive| KNOWN.contains(directive))
+			.map(|directive| quote(directive))
+			.collect();
+
+		if !directives.is_empty() {
+			self.set_header("Clear-Site-Data", directives.join(", "));
+		}
+		self
+	}
+
+	/// Sets `Origin-Agent-Cluster` to `?1`, asking the browser to isolate the origin
+	/// in its own agent cluster, or to `?0` to opt out.
+	pub fn with_origin_agent_cluster(mut self, isolated: bool) -> Self {
+		let value = if isolated { "?1" } else { "?0" };
+		self.set_header("Origin-Agent-Cluster", value.to_string());
+		self
+	}
+
+	/// Sets `Timing-Allow-Origin`, letting `origin` (or every origin with `*`) read
+	/// detailed Resource Timing data for this response.
+	pub fn with_timing_allow_origin(mut self, origin: &str) -> Self {
+		self.set_header("Timing-Allow-Origin", origin.to_string());
+		self
+	}
+
+	/// Appends a `Warning` header in the `code agent "text"` format, e.g. for a
+	/// transformed `203` or an `226 IM Used` response. An existing `Warning` header
+	/// is extended whatever the case of its name.
+	pub fn with_warning(mut self, code: u16, agent: &str, text: &str) -> Self {
+		let warning = format!("{:03} {} {}", code, agent, quote(text));
+
+		let headers = self.headers_mut();
+		match headers
+			.iter_mut()
+			.find(|(k, _)| k.eq_ignore_ascii_case("Warning"))
+		{
+			Some((_, existing)) => {
+				existing.push_str(", ");
+				existing.push_str(&warning);
+			}
+			None => {
+				headers.insert("Warning", warning);
+			}
+		}
+
+		self
+	}
+
+	/// Enables Network Error Logging with a `NEL` header, sending reports to the
+	/// `report_to` group for `max_age` seconds. Pair it with `with_report_to`.
+	pub fn with_nel(mut self, max_age: u32, report_to: &str) -> Self {
+		self.set_header(
+			"NEL",
+			format!(
+				"{{\"report_to\":{},\"max_age\":{}}}",
+				json_string(report_to),
+				max_age
+			)
+		);
+		self
+	}
+
+	/// Sets `Report-To`, declaring the `group` of endpoint URLs that reports are
+	/// delivered to for `max_age` seconds.
+	pub fn with_report_to(mut self, group: &str, max_age: u32, endpoints: &[&str]) -> Self {
+		let endpoints: Vec<String> = endpoints
+			.iter()
+			.map(|url| format!("{{\"url\":{}}}", json_string(url)))
+			.collect();
+
+		self.set_header(
+			"Report-To",
+			format!(
+				"{{\"group\":{},\"max_age\":{},\"endpoints\":[{}]}}",
+				json_string(group),
+				max_age,
+				endpoints.join(",")
+			)
+		);
+		self
+	}
+
+	/// Sets a `Content-Security-Policy` from `policy_template`, replacing every
+	/// `{nonce}` with a fresh random nonce. The nonce is returned so it can be put
+	/// in the `nonce` attribute of inline `<script>` and `<style>` tags.
+	///
+	/// The nonce comes from std's OS-seeded hasher keys, which works on every
+	/// platform. Use `with_csp_nonce_from` to supply bytes from your own generator.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let (response, nonce) = HttpResponse::ok(None, None).with_csp_nonce("script-src 'nonce-{nonce}'");
+	/// let policy = &response.headers.unwrap()["Content-Security-Policy"];
+	/// assert_eq!(&format!("script-src 'nonce-{}'", nonce), policy);
+	/// ```
+	pub fn with_csp_nonce(self, policy_template: &str) -> (Self, String) {
+		self.with_csp_nonce_from(policy_template, random_bytes())
+	}
+
+	/// Like `with_csp_nonce`, with the nonce encoded from `random`, which should come
+	/// from a cryptographically secure generator.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let (response, nonce) = HttpResponse::ok(None, None).with_csp_nonce_from("{nonce}", [0; 16]);
+	/// assert_eq!("AAAAAAAAAAAAAAAAAAAAAA==", nonce);
+	/// ```
+	pub fn with_csp_nonce_from(mut self, policy_template: &str, random: [u8; 16]) -> (Self, String) {
+		let nonce = base64(&random);
+		self.set_header(
+			"Content-Security-Policy",
+			policy_template.replace("{nonce}", &nonce)
+		);
+		(self, nonce)
+	}
+
+	/// Sets `Content-Type` from the start of the body when none is set: `{` or `[`
+	/// gives `application/json`, `<!DOCTYPE` or `<html` gives `text/html` and anything
+	/// else `text/plain`. Constructors given `None` headers already set `text/html`,
+	/// so build the response with headers or through `HttpResponse::builder`.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let response = HttpResponse::builder().body("{\"ok\":true}").infer_content_type().build().unwrap();
+	/// assert_eq!(Some("application/json"), response.header("Content-Type"));
+	/// ```
+	pub fn with_inferred_content_type(mut self) -> Self {
+		if self.header("Content-Type").is_some() {
+			return self;
+		}
+
+		let body = self.raw_body().trim_ascii_start();
+		let starts_with = |prefix: &[u8]| {
+			body
+				.get(..prefix.len())
+				.is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+		};
+
+		let content_type = if starts_with(b"{") || starts_with(b"[") {
+			"application/json"
+		} else if starts_with(b"<!DOCTYPE") || starts_with(b"<html") {
+			"text/html"
+		} else {
+			"text/plain"
+		};
+
+		self.set_header("Content-Type", content_type.to_string());
+		self
+	}
+
+	/// A `200 OK` plain text response with `charset` in its `Content-Type`. A charset
+	/// that isn't a valid token would corrupt the header, so it's replaced by `utf-8`.
+	pub fn text(body: String, charset: &str) -> Self {
+		let charset = if is_token(charset) { charset } else { "utf-8" };
+
+		let mut headers = HashMap::new();
+		headers.insert("Content-Type", format!("text/plain; charset={}", charset));
+		Self::ok(Some(headers), Some(body))
+	}
+
+	/// Starts a `200 OK` response, see `HttpResponseBuilder`.
+	pub fn builder() -> HttpResponseBuilder<'a> {
+		HttpResponseBuilder::default()
+	}
+
+	/// A redirect to `location` with a `3xx` `status`. Returns `None` for any other
+	/// status, an unregistered one, or a `location` with CR, LF or NUL, which would
+	/// let it inject headers.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let response = HttpResponse::redirect(303, "/orders/7").unwrap();
+	/// assert!(response.to_string().contains("Location:/orders/7\r\n"));
+	/// assert_eq!(None, HttpResponse::redirect(200, "/"));
+	/// ```
+	pub fn redirect(status: u16, location: &'a str) -> Option<Self> {
+		if !(300..400).contains(&status) || location.contains(['\r', '\n', '\0']) {
+			return None;
+		}
+
+		let (status_code, status_text) = status_for(status)?;
+		let mut response = Self::from_status(None, None, status_code, status_text);
+		response.set_header("Location", location.to_string());
+		Some(response)
+	}
+
+	/// A `308 Permanent Redirect` to `location`, see `redirect`.
+	pub fn redirect_permanent(location: &'a str) -> Option<Self> {
+		Self::redirect(308, location)
+	}
+
+	/// A `307 Temporary Redirect` to `location`, see `redirect`.
+	pub fn redirect_temporary(location: &'a str) -> Option<Self> {
+		Self::redirect(307, location)
+	}
+
+	/// A `200 OK` response with `pairs` as an `application/x-www-form-urlencoded` body.
+	pub fn form_urlencoded(pairs: &[(&str, &str)]) -> Self {
+		let mut headers = HashMap::new();
+		headers.insert(
+			"Content-Type",
+			"application/x-www-form-urlencoded".to_string()
+		);
+		Self::ok(Some(headers), Some(encode_pairs(pairs)))
+	}
+
+	/// A response for `code` whose body is only the reason phrase, so nothing about
+	/// the underlying error leaks to clients. Unregistered codes become a `500`.
+	pub fn opaque_error(code: u16) -> Self {
+		let (status_code, status_text) = status_for(code).unwrap_or(("500", "Internal Server Error"));
+
+		let mut headers = HashMap::new();
+		headers.insert("Content-Type", "text/plain".to_string());
+		Self::from_status(
+			Some(headers),
+			Some(status_text.to_string()),
+			status_code,
+			status_text
+		)
+	}
+
+	/// A `200 OK` whose body is `req` serialized back to wire format, sent as
+	/// `message/http`. Meant for test harnesses and debugging. The body is empty
+	/// for a request without a wire form, see `Display for HttpRequest`.
+	pub fn echo(req: &HttpRequest) -> Self {
+		let mut headers = HashMap::new();
+		headers.insert("Content-Type", "message/http".to_string());
+		Self::ok(Some(headers), Some(String::from(req.clone())))
+	}
+
+	/// Sets a binary body such as an image, sent as is with its byte length as the
+	/// `Content-Length`. It replaces any other body. Converting the response to a
+	/// `String` is lossy for bodies that aren't UTF-8, use `into_bytes` instead.
+	pub fn with_bytes(mut self, bytes: Vec<u8>) -> Self {
+		self.body = None;
+		self.body_source = Some(bytes.into());
+		self
+	}
+
+	/// The response in wire format. A body reader isn't consumed, only `send_response`
+	/// streams it.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let bytes = HttpResponse::ok(None, None).with_bytes(vec![0xff, 0xd8]).into_bytes();
+	/// assert!(bytes.ends_with(b"Content-Length: 2\r\n\r\n\xff\xd8"));
+	/// ```
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.to_bytes()
+	}
+
+	/// Streams the body from `reader` instead of buffering it. `len` is sent as the
+	/// `Content-Length` and exactly that many bytes are copied by `send_response`.
+	/// The reader replaces any other body and is left out when converting the
+	/// response to a `String`, since it can only be consumed once.
+	pub fn set_body_reader(&mut self, reader: impl Read + Send + 'a, len: usize) {
+		self.body = None;
+		self.body_source = Some(BodySource(Source::Reader(
+			Arc::new(Mutex::new(Box::new(reader))),
+			len
+		)));
+	}
+
+	/// The body as sent, text or binary. `None` when it's streamed from a reader.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let response = HttpResponse::ok(None, None).with_bytes(vec![0xff, 0xd8]);
+	/// assert_eq!(Some(&[0xff, 0xd8][..]), response.body_bytes());
+	/// ```
+	pub fn body_bytes(&self) -> Option<&[u8]> {
+		match &self.body_source {
+			Some(source) => source.as_bytes(),
+			None => Some(self.body.as_deref().map_or(&[], str::as_bytes))
+		}
+	}
+}
+
+impl<'a> HttpResponse<'a> {
+	/// Value of the header `name`, ignoring ASCII case in the name. Cookies added
+	/// with `add_cookie` are in `cookies`, not in the headers.
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self
+			.headers
+			.iter()
+			.flatten()
+			.find(|(k, _)| k.eq_ignore_ascii_case(name))
+			.map(|(_, v)| v.as_str())
+	}
+
+	fn headers_mut(&mut self) -> &mut HashMap<&'a str, String> {
+		self.headers.get_or_insert_with(HashMap::new)
+	}
+
+	/// Sets `name`, replacing any existing header spelled with a different case.
+	fn set_header(&mut self, name: &'a str, value: String) {
+		let headers = self.headers_mut();
+		headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
+		headers.insert(name, value);
+	}
+
+	fn headers(&self, eol: &str) -> String {
+		let mut header_string = String::new();
+
+		for (k, v) in self.headers.iter().flatten() {
+			header_string += &format!("{}:{}{}", k, v, eol);
+		}
+		for cookie in &self.cookies {
+			header_string += &format!("Set-Cookie:{}{}", cookie, eol);
+		}
+
+		header_string
+	}
+
+	/// The buffered body, binary or text, empty for a reader.
+	fn raw_body(&self) -> &[u8] {
+		self.body_bytes().unwrap_or(&[])
+	}
+
+	fn body_len(&self) -> usize {
+		match &self.body_source {
+			Some(source) => source.len(),
+			None => self.body.as_ref().map_or(0, String::len)
+		}
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = self.head("\r\n", self.body_len()).into_bytes();
+		bytes.extend_from_slice(self.raw_body());
+		bytes
+	}
+
+	fn block(&self, eol: &str, body_len: usize) -> String {
+		// A Content-Length set by the caller wins, two length headers would be ambiguous.
+		// Interim 1xx responses have no body, so they can't carry one.
+		if self.header("Content-Length").is_some() || self.status_code.starts_with('1') {
+			return format!("{}{}", self.headers(eol), eol);
+		}
+
+		format!(
+			"{}Content-Length: {}{}{}",
+			self.headers(eol),
+			body_len,
+			eol,
+			eol
+		)
+	}
+
+	fn head(&self, eol: &str, body_len: usize) -> String {
+		format!(
+			"{} {} {}{}{}",
+			self.version,
+			self.status_code,
+			self.status_text,
+			eol,
+			self.block(eol, body_len)
+		)
+	}
 }
 
-impl<'a> Default for HttpResponse<'a> {
-	fn default() -> Self {
-		Self {
-			version: "HTTP/1.1",
-			status_code: "200",
-			status_text: "OK",
-			headers: None,
-			body: None
+impl<'a> From<HttpResponse<'a>> for String {
+	#[inline(always)]
+	fn from(res: HttpResponse) -> String {
+		String::from(&res)
+	}
+}
+
+impl<'a> From<&HttpResponse<'a>> for String {
+	fn from(res: &HttpResponse) -> String {
+		res.to_string_with_eol("\r\n")
+	}
+}
+
+/// Maps an I/O error to a generic error response, so handlers can use `?` on I/O calls:
+/// `NotFound` gives a `404`, `PermissionDenied` a `403` and anything else a `500`.
+impl<'a> From<io::Error> for HttpResponse<'a> {
+	fn from(err: io::Error) -> Self {
+		match err.kind() {
+			io::ErrorKind::NotFound => Self::opaque_error(404),
+			io::ErrorKind::PermissionDenied => Self::opaque_error(403),
+			_ => Self::opaque_error(500)
+		}
+	}
+}
+
+impl<'a> Display for HttpResponse<'a> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", String::from(self))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::cookie::SameSite;
+	use crate::response::*;
+
+	#[test]
+	fn test_response_struct_creation_200() {
+		let response_actual = HttpResponse::ok(None, Some("Item was shipped on 21st Dec 2020".into()));
+
+		let response_expected = HttpResponse {
+			version: "HTTP/1.1",
+			status_code: "200",
+			status_text: "OK",
+			headers: {
+				let mut h = HashMap::new();
+				h.insert("Content-Type", "text/html".to_string());
+				Some(h)
+			},
+			body: Some("Item was shipped on 21st Dec 2020".into()),
+			body_source: None,
+			cookies: Vec::new()
+		};
+
+		assert_eq!(response_actual, response_expected);
+	}
+
+	#[test]
+	fn test_response_struct_creation_404() {
+		let response_actual =
+			HttpResponse::not_found(None, Some("Item was shipped on 21st Dec 2020".into()));
+
+		let response_expected = HttpResponse {
+			version: "HTTP/1.1",
+			status_code: "404",
+			status_text: "Not Found",
+			headers: {
+				let mut h = HashMap::new();
+				h.insert("Content-Type", "text/html".to_string());
+				Some(h)
+			},
+			body: Some("Item was shipped on 21st Dec 2020".into()),
+			body_source: None,
+			cookies: Vec::new()
+		};
+
+		assert_eq!(response_actual, response_expected);
+	}
+
+	#[test]
+	fn test_http_response_creation() {
+		let response_expected = HttpResponse {
+			version: "HTTP/1.1",
+			status_code: "404",
+			status_text: "Not Found",
+			headers: {
+				let mut h = HashMap::new();
+				h.insert("Content-Type", "text/html".to_string());
+				Some(h)
+			},
+			body: Some("Item was shipped on 21st Dec 2020".into()),
+			body_source: None,
+			cookies: Vec::new()
+		};
+
+		let http_string: String = response_expected.into();
+		let response_actual = "HTTP/1.1 404 Not Found\r\nContent-Type:text/html\r\nContent-Length: 33\r\n\r\nItem was shipped on 21st Dec 2020";
+
+		assert_eq!(http_string, response_actual);
+	}
+
+	#[test]
+	fn test_set_body_reader() {
+		let data = "streamed from a reader";
+		let mut response = HttpResponse::ok(None, Some("ignored".into()));
+		response.set_body_reader(io::Cursor::new(data), data.len());
+		assert_eq!(
+			"HTTP/1.1 200 OK\r\nContent-Type:text/html\r\nContent-Length: 0\r\n\r\n",
+			response.to_string()
+		);
+
+		assert_eq!(None, response.body);
+		assert_eq!(None, response.body_bytes());
+		assert_eq!(response, response.clone());
+
+		let mut sent = Vec::new();
+		let written = std::thread::scope(|s| {
+			s.spawn(|| response.send_response(&mut sent))
+				.join()
+				.unwrap()
+		})
+		.unwrap();
+
+		assert_eq!(sent.len(), written);
+		assert_eq!(
+			format!(
+				"HTTP/1.1 200 OK\r\nContent-Type:text/html\r\nContent-Length: {}\r\n\r\n{}",
+				data.len(),
+				data
+			),
+			String::from_utf8(sent).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_set_body_reader_short() {
+		let mut response = HttpResponse::ok(None, None);
+		response.set_body_reader(io::Cursor::new("short"), 10);
+
+		let err = response.send_response(&mut Vec::new()).unwrap_err();
+		assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+	}
+
+	/// Accepts a few bytes, then fails like a closed socket.
+	struct BrokenPipe(usize);
+
+	impl Write for BrokenPipe {
+		fn write(&mut self, buf: &[u8]) -> Result<usize> {
+			if self.0 == 0 {
+				return Err(io::ErrorKind::BrokenPipe.into());
+			}
+			let n = buf.len().min(self.0);
+			self.0 -= n;
+			Ok(n)
+		}
+
+		fn flush(&mut self) -> Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_send_response_bytes_and_errors() {
+		let response = HttpResponse::ok(None, Some("hello".into()));
+
+		let mut sent = Vec::new();
+		let written = response.send_response(&mut sent).unwrap();
+		assert_eq!(sent.len(), written);
+		assert_eq!(String::from(&response).into_bytes(), sent);
+
+		let err = response.send_response(&mut BrokenPipe(10)).unwrap_err();
+		assert_eq!(io::ErrorKind::BrokenPipe, err.kind());
+	}
+
+	#[test]
+	fn test_with_warning() {
+		let response = HttpResponse::non_authoritative_information(None, None)
+			.with_warning(214, "proxy.example", "Transformation \"applied\"")
+			.with_warning(299, "-", "Deprecated");
+
+		assert_eq!(
+			"214 proxy.example \"Transformation \\\"applied\\\"\", 299 - \"Deprecated\"",
+			response.headers.unwrap()["Warning"]
+		);
+
+		let mut headers = HashMap::new();
+		headers.insert("warning", "110 - \"Stale\"".to_string());
+		let response = HttpResponse::ok(Some(headers), None).with_warning(299, "-", "Deprecated");
+		let headers = response.headers.unwrap();
+		assert_eq!(1, headers.len());
+		assert_eq!("110 - \"Stale\", 299 - \"Deprecated\"", headers["warning"]);
+	}
+
+	#[test]
+	fn test_common_status_constructors() {
+		let responses = [
+			(
+				HttpResponse::moved_permanently(None, None),
+				"301 Moved Permanently"
+			),
+			(HttpResponse::conflict(None, None), "409 Conflict"),
+			(
+				HttpResponse::unprocessable_entity(None, None),
+				"422 Unprocessable Entity"
+			),
+			(
+				HttpResponse::too_many_requests(None, None),
+				"429 Too Many Requests"
+			),
+			(HttpResponse::bad_gateway(None, None), "502 Bad Gateway"),
+			(
+				HttpResponse::service_unavailable(None, None),
+				"503 Service Unavailable"
+			),
+			(
+				HttpResponse::gateway_timeout(None, None),
+				"504 Gateway Timeout"
+			)
+		];
+
+		for (response, status) in responses {
+			assert!(String::from(response).starts_with(&format!("HTTP/1.1 {}\r\n", status)));
+		}
+	}
+
+	#[test]
+	fn test_im_used() {
+		let response: String = HttpResponse::im_used(None, None).into();
+		assert!(response.starts_with("HTTP/1.1 226 IM Used\r\n"));
+	}
+
+	#[test]
+	fn test_opaque_error() {
+		let response = HttpResponse::opaque_error(503);
+		assert_eq!("503", response.status_code);
+		assert_eq!(Some("Service Unavailable".into()), response.body);
+		assert_eq!("text/plain", response.headers.unwrap()["Content-Type"]);
+
+		let response = HttpResponse::opaque_error(599);
+		assert_eq!("500", response.status_code);
+		assert_eq!(Some("Internal Server Error".into()), response.body);
+	}
+
+	#[test]
+	fn test_headers_block() {
+		let mut headers = HashMap::new();
+		headers.insert("Content-Type", "text/plain".to_string());
+		headers.insert("Cache-Control", "no-store".to_string());
+		let block = HttpResponse::ok(Some(headers), Some("hello".into())).headers_block();
+
+		assert!(block.contains("Content-Type:text/plain\r\n"));
+		assert!(block.contains("Cache-Control:no-store\r\n"));
+		assert!(block.contains("Content-Length: 5\r\n"));
+		assert!(block.ends_with("\r\n\r\n"));
+		assert_eq!(4, block.matches("\r\n").count());
+
+		let block = HttpResponse::default().headers_block();
+		assert_eq!("Content-Length: 0\r\n\r\n", block);
+	}
+
+	#[test]
+	fn test_echo() {
+		let req: HttpRequest =
+			String::from("POST /echo HTTP/1.1\r\nHost: localhost\r\nX-Trace: abc\r\n\r\nping").into();
+		let response = HttpResponse::echo(&req);
+		let body = response.body.as_deref().unwrap();
+
+		assert_eq!("200", response.status_code);
+		assert_eq!(
+			"message/http",
+			response.headers.as_ref().unwrap()["Content-Type"]
+		);
+		assert!(body.starts_with("POST /echo HTTP/1.1\r\n"));
+		assert!(body.contains("Host: localhost\r\n"));
+		assert!(body.contains("X-Trace: abc\r\n"));
+		assert!(body.ends_with("\r\n\r\nping"));
+	}
+
+	#[test]
+	fn test_content_length_consistent() {
+		let with_length = |len: &str| {
+			let mut headers = HashMap::new();
+			headers.insert("content-length", len.to_string());
+			HttpResponse::ok(Some(headers), Some("héllo".into()))
+		};
+
+		assert!(with_length("6").content_length_consistent());
+		assert!(!with_length("5").content_length_consistent());
+		assert!(!with_length("six").content_length_consistent());
+		assert!(HttpResponse::ok(None, Some("hello".into())).content_length_consistent());
+	}
+
+	#[test]
+	fn test_from_io_error() {
+		let status = |kind: io::ErrorKind| HttpResponse::from(io::Error::from(kind)).status_code;
+
+		assert_eq!("404", status(io::ErrorKind::NotFound));
+		assert_eq!("403", status(io::ErrorKind::PermissionDenied));
+		assert_eq!("500", status(io::ErrorKind::BrokenPipe));
+
+		let response: HttpResponse = std::fs::read_to_string("/definitely/not/here")
+			.unwrap_err()
+			.into();
+		assert_eq!(Some("Not Found".into()), response.body);
+	}
+
+	#[test]
+	fn test_common_log() {
+		let req: HttpRequest = String::from("GET /index.html?lang=en HTTP/1.1\r\n\r\n").into();
+		let line = common_log(&req, &HttpResponse::not_found(None, None), "127.0.0.1", 209);
+
+		assert!(line.starts_with("127.0.0.1 - - ["));
+		assert!(line.ends_with("] \"GET /index.html?lang=en HTTP/1.1\" 404 209"));
+		// [dd/Mon/yyyy:hh:mm:ss +0000]
+		let date = &line[line.find('[').unwrap() + 1..line.find(']').unwrap()];
+		assert_eq!(26, date.len());
+		assert!(date.ends_with(" +0000"));
+
+		let line = common_log(&req, &HttpResponse::not_modified(None, None), "::1", 0);
+		assert!(line.ends_with("\" 304 -"));
+	}
+
+	#[test]
+	fn test_accept_ranges() {
+		let mut headers = HashMap::new();
+		headers.insert("accept-ranges", "bytes".to_string());
+		let mut response = HttpResponse::ok(Some(headers), None);
+		assert_eq!(Some("bytes"), response.accept_ranges());
+
+		response.set_accept_ranges("none");
+		assert_eq!(Some("none"), response.accept_ranges());
+		assert_eq!(1, response.headers.as_ref().unwrap().len());
+
+		assert_eq!(None, HttpResponse::ok(None, None).accept_ranges());
+	}
+
+	#[test]
+	fn test_validate_limits() {
+		let names = ["X-A", "X-B", "X-C", "X-D"];
+		let headers = names.iter().map(|n| (*n, "1".to_string())).collect();
+		let response = HttpResponse::ok(Some(headers), None);
+
+		assert_eq!(Ok(()), response.validate_limits(4, 1024));
+		assert_eq!(
+			Err(SerializeError::TooManyHeaders),
+			response.validate_limits(3, 1024)
+		);
+		assert_eq!(
+			Err(SerializeError::HeadersTooLarge),
+			response.validate_limits(4, 16)
+		);
+	}
+
+	#[test]
+	fn test_to_string_with_eol() {
+		let response = HttpResponse::ok(None, Some("line one\r\nline two".into()));
+
+		assert_eq!(
+			"HTTP/1.1 200 OK\nContent-Type:text/html\nContent-Length: 18\n\nline one\r\nline two",
+			response.to_string_with_eol("\n")
+		);
+		assert_eq!(String::from(&response), response.to_string_with_eol("\r\n"));
+	}
+
+	#[test]
+	fn test_content_range() {
+		let with_range = |value: &str| {
+			let mut headers = HashMap::new();
+			headers.insert("Content-Range", value.to_string());
+			HttpResponse::partial_content(Some(headers), None).content_range()
+		};
+
+		assert_eq!(Some((0, 499, Some(1234))), with_range("bytes 0-499/1234"));
+		assert_eq!(Some((0, 499, None)), with_range("bytes 0-499/*"));
+		assert_eq!(None, with_range("bytes */1234"));
+		assert_eq!(None, with_range("bytes 500-0/1234"));
+		assert_eq!(None, with_range("bytes 0-1234/1234"));
+		assert_eq!(None, HttpResponse::ok(None, None).content_range());
+	}
+
+	#[test]
+	fn test_map_body() {
+		let response = HttpResponse::not_found(None, Some("missing".into()));
+		let headers = response.headers.clone();
+		let response = response.map_body(|b| b.to_uppercase());
+
+		assert_eq!(Some("MISSING".into()), response.body);
+		assert_eq!("404", response.status_code);
+		assert_eq!(headers, response.headers);
+
+		let response = HttpResponse::ok(None, None).map_body(|b| b + "appended");
+		assert_eq!(Some("appended".into()), response.body);
+
+		let response = HttpResponse::ok(None, None).with_bytes(b"{ }".to_vec());
+		let response = response.map_body(|_| unreachable!());
+		assert_eq!(Some(&b"{ }"[..]), response.body_bytes());
+
+		let mut response = HttpResponse::ok(None, None);
+		response.set_body_reader(io::Cursor::new("streamed"), 8);
+		let response = response.map_body(|_| unreachable!());
+		assert_eq!(Framing::Length(8), response.body_framing());
+	}
+
+	#[test]
+	fn test_body_framing() {
+		let response = HttpResponse::ok(None, Some("hello".into()));
+		assert_eq!(Framing::Length(5), response.body_framing());
+
+		let mut headers = HashMap::new();
+		headers.insert("Transfer-Encoding", "gzip, chunked".to_string());
+		let response = HttpResponse::ok(Some(headers), None);
+		assert_eq!(Framing::Chunked, response.body_framing());
+
+		let mut headers = HashMap::new();
+		headers.insert("Transfer-Encoding", "gzip".to_string());
+		let response = HttpResponse::ok(Some(headers), None);
+		assert_eq!(Framing::UntilClose, response.body_framing());
+
+		let response = HttpResponse::no_content(None, None);
+		assert_eq!(Framing::None, response.body_framing());
+		assert_eq!(
+			Framing::None,
+			HttpResponse::not_modified(None, None).body_framing()
+		);
+	}
+
+	#[test]
+	fn test_should_continue() {
+		let upload = |len: usize| -> HttpRequest {
+			format!(
+				"PUT /upload HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: {}\r\n\r\n",
+				len
+			)
+			.into()
+		};
+
+		let status = |req: &HttpRequest| should_continue(req, 4096).map(|res| res.status_code);
+
+		assert_eq!(Some("100"), status(&upload(1024)));
+		assert_eq!(Some("100"), status(&upload(4096)));
+		assert_eq!(Some("413"), status(&upload(4097)));
+		assert_eq!(
+			"HTTP/1.1 100 Continue\r\n\r\n",
+			should_continue(&upload(1024), 4096).unwrap().to_string()
+		);
+
+		let expect = |value: &str| -> HttpRequest {
+			format!("PUT /upload HTTP/1.1\r\n{}Content-Length: 1\r\n\r\n", value).into()
+		};
+		assert_eq!(Some("100"), status(&expect("expect: 100-Continue\r\n")));
+		assert_eq!(None, status(&expect("")));
+		assert_eq!(Some("417"), status(&expect("Expect: 200-ok\r\n")));
+	}
+
+	#[test]
+	fn test_unauthorized_bearer() {
+		let response = HttpResponse::unauthorized_bearer("api", Some("invalid_token"));
+		assert_eq!("401", response.status_code);
+		assert_eq!(
+			"Bearer realm=\"api\", error=\"invalid_token\"",
+			response.headers.unwrap()["WWW-Authenticate"]
+		);
+
+		let response = HttpResponse::unauthorized_bearer("my \"app\"", None);
+		assert_eq!(
+			"Bearer realm=\"my \\\"app\\\"\"",
+			response.headers.unwrap()["WWW-Authenticate"]
+		);
+	}
+
+	#[test]
+	fn test_dedup_singleton_headers() {
+		let mut response = HttpResponse::ok(None, Some("{}".into()));
+		response
+			.headers
+			.as_mut()
+			.unwrap()
+			.insert("content-type", "application/json".to_string());
+		let lowercase_block = response.headers_block().to_ascii_lowercase();
+		assert_eq!(2, lowercase_block.matches("content-type:").count());
+
+		response.dedup_singleton_headers();
+		let headers = response.headers.as_ref().unwrap();
+		assert_eq!(1, headers.len());
+		assert_eq!("application/json", headers["Content-Type"]);
+	}
+
+	#[test]
+	fn test_with_pagination_links() {
+		let mut response = HttpResponse::ok(None, None);
+		response.with_pagination_links(Some("/items?page=3"), Some("/items?page=1"), None, None);
+
+		assert_eq!(
+			"</items?page=3>; rel=\"next\", </items?page=1>; rel=\"prev\"",
+			response.headers.unwrap()["Link"]
+		);
+	}
+
+	#[test]
+	fn test_with_server_timing() {
+		let mut response = HttpResponse::ok(None, None);
+		response.with_server_timing(&[
+			("db", Some(53.2), Some("query")),
+			("cache", None, Some("hit")),
+			("total", Some(80.0), None)
+		]);
+
+		assert_eq!(
+			"db;dur=53.2;desc=\"query\", cache;desc=\"hit\", total;dur=80",
+			response.headers.unwrap()["Server-Timing"]
+		);
+	}
+
+	#[test]
+	fn test_add_cookie() {
+		let mut response = HttpResponse::ok(None, None);
+		let session = CookieOptions {
+			path: Some("/".into()),
+			http_only: true,
+			secure: true,
+			..Default::default()
+		};
+		response.add_cookie("session", "s3cr3t", session).unwrap();
+		let csrf = CookieOptions {
+			same_site: Some(SameSite::Strict),
+			..Default::default()
+		};
+		response.add_cookie("csrf", "t0k3n", csrf).unwrap();
+
+		assert_eq!(
+			Err(CookieError::InvalidAttribute),
+			response.add_cookie(
+				"theme",
+				"dark",
+				CookieOptions {
+					path: Some("/; Domain=evil.example".into()),
+					..Default::default()
+				}
+			)
+		);
+		assert_eq!(2, response.cookies.len());
+		assert_eq!(None, response.header("Set-Cookie"));
+		assert_eq!(Ok(()), response.validate_limits(3, 1024));
+		assert_eq!(
+			Err(SerializeError::TooManyHeaders),
+			response.validate_limits(2, 1024)
+		);
+
+		let response = String::from(response);
+		assert!(response.contains("\r\nSet-Cookie:session=s3cr3t; Path=/; Secure; HttpOnly\r\n"));
+		assert!(response.contains("\r\nSet-Cookie:csrf=t0k3n; SameSite=Strict\r\n"));
+		assert_eq!(2, response.matches("Set-Cookie:").count());
+	}
+
+	#[test]
+	fn test_display_matches_string() {
+		let mut response = HttpResponse::not_found(None, Some("nothing here".into()));
+		response
+			.add_cookie("seen", "1", CookieOptions::default())
+			.unwrap();
+
+		assert_eq!(String::from(&response), format!("{}", response));
+	}
+
+	#[test]
+	fn test_head_string() {
+		let response = HttpResponse::ok(None, Some("secret=hunter2".into()));
+
+		assert_eq!(
+			"HTTP/1.1 200 OK\r\nContent-Type:text/html\r\nContent-Length: 14\r\n\r\n[14 bytes body omitted]",
+			response.head_string()
+		);
+	}
+
+	#[test]
+	fn test_redirect() {
+		let response: String = HttpResponse::redirect(301, "https://example.com/new")
+			.unwrap()
+			.into();
+		assert!(response.starts_with("HTTP/1.1 301 Moved Permanently\r\n"));
+		assert!(response.contains("\r\nLocation:https://example.com/new\r\n"));
+
+		let response = HttpResponse::redirect_permanent("/b").unwrap();
+		assert_eq!(
+			("308", Some("/b")),
+			(response.status_code, response.header("Location"))
+		);
+
+		let response = HttpResponse::redirect_temporary("/c").unwrap();
+		assert_eq!(
+			("307", Some("/c")),
+			(response.status_code, response.header("Location"))
+		);
+
+		assert_eq!(None, HttpResponse::redirect(404, "/"));
+		assert_eq!(None, HttpResponse::redirect(399, "/"));
+
+		for location in ["/a\r\nSet-Cookie: x=1", "/a\nb", "/a\0b"] {
+			assert_eq!(None, HttpResponse::redirect(302, location));
+			assert_eq!(None, HttpResponse::redirect_permanent(location));
+			assert_eq!(None, HttpResponse::redirect_temporary(location));
 		}
 	}
-}
 
-impl<'a> HttpResponse<'a> {
-	/// Deprecated. Use `HttpResponse::ok(...)`, `HttpResponse::bad_request(...)`, etc instead.
-	#[deprecated]
-	pub fn new(
-		status_code: &'a str,
-		headers: Option<HashMap<&'a str, String>>,
-		body: Option<String>
-	) -> HttpResponse<'a> {
-		let mut response: HttpResponse<'a> = HttpResponse::default();
+	#[test]
+	fn test_validate() {
+		let mut headers = HashMap::new();
+		headers.insert("X-Name", "Jürgen".to_string());
+		headers.insert("X-Bell", "ring\u{7}".to_string());
+		headers.insert("X-Ok", "plain value\twith tab".to_string());
+		let response = HttpResponse::ok(Some(headers), None);
 
-		if status_code != "200" {
-			response.status_code = status_code;
-		};
+		assert_eq!(
+			Err(SerializeError::InvalidHeaderValues(vec![
+				"X-Bell".into(),
+				"X-Name".into()
+			])),
+			response.validate()
+		);
 
-		response.headers = match &headers {
-			Some(_h) => headers,
-			None => {
-				let mut h: HashMap<&str, String> = HashMap::new();
-				h.insert("Content-Type", "text/plain".to_string());
-				Some(h)
-			}
+		let mut response = HttpResponse::ok(None, None);
+		response
+			.add_cookie("id", "a1", CookieOptions::default())
+			.unwrap();
+		assert_eq!(Ok(()), response.validate());
+
+		response.cookies.push(Cookie::new("bad", "line\rbreak"));
+		assert_eq!(
+			Err(SerializeError::InvalidHeaderValues(vec![
+				"Set-Cookie".into()
+			])),
+			response.validate()
+		);
+	}
+
+	#[test]
+	fn test_with_inferred_content_type() {
+		let infer = |body: &str| {
+			let response = HttpResponse::ok(Some(HashMap::new()), Some(body.into()));
+			response
+				.with_inferred_content_type()
+				.header("Content-Type")
+				.map(str::to_string)
 		};
 
-		response.status_text = match response.status_code {
-			"200" => "OK",
-			"400" => "Bad Request",
-			"404" => "Not Found",
-			"500" => "Internal Server Error",
-			_ => ""
+		assert_eq!(Some("application/json".into()), infer("  {\"a\": 1}"));
+		assert_eq!(Some("application/json".into()), infer("[1, 2]"));
+		assert_eq!(Some("text/html".into()), infer("<!doctype html><p>hi"));
+		assert_eq!(Some("text/html".into()), infer("<html></html>"));
+		assert_eq!(Some("text/plain".into()), infer("hello"));
+		assert_eq!(Some("text/plain".into()), infer(""));
+
+		let mut headers = HashMap::new();
+		headers.insert("content-type", "application/ld+json".to_string());
+		let response = HttpResponse::ok(Some(headers), Some("{}".into())).with_inferred_content_type();
+		assert_eq!(Some("application/ld+json"), response.header("Content-Type"));
+
+		let response = HttpResponse::builder()
+			.body("<html>")
+			.infer_content_type()
+			.build()
+			.unwrap();
+		assert_eq!(Some("text/html"), response.header("Content-Type"));
+	}
+
+	#[test]
+	fn test_header() {
+		let mut headers = HashMap::new();
+		headers.insert("Content-Type", "application/json".to_string());
+		let response = HttpResponse::created(Some(headers), None);
+
+		assert_eq!(Some("application/json"), response.header("content-type"));
+		assert_eq!(None, response.header("Location"));
+		assert_eq!("201", response.status_code);
+	}
+
+	#[test]
+	fn test_with_alt_svc() {
+		let response: String = HttpResponse::ok(None, None)
+			.with_alt_svc("h3=\":443\"; ma=86400")
+			.into();
+		assert!(response.contains("\r\nAlt-Svc:h3=\":443\"; ma=86400\r\n"));
+
+		let response = HttpResponse::ok(None, None).with_alt_svc("  ");
+		assert!(!response.headers.unwrap().contains_key("Alt-Svc"));
+	}
+
+	#[test]
+	fn test_with_clear_site_data() {
+		let response =
+			HttpResponse::ok(None, None).with_clear_site_data(&["cookies", "storage", "bogus"]);
+		assert_eq!(
+			"\"cookies\", \"storage\"",
+			response.headers.unwrap()["Clear-Site-Data"]
+		);
+
+		let response = HttpResponse::ok(None, None).with_clear_site_data(&["everything"]);
+		assert!(!response.headers.unwrap().contains_key("Clear-Site-Data"));
+	}
+
+	#[test]
+	fn test_with_origin_agent_cluster() {
+		let response = HttpResponse::ok(None, None).with_origin_agent_cluster(true);
+		assert_eq!("?1", response.headers.unwrap()["Origin-Agent-Cluster"]);
+
+		let response = HttpResponse::ok(None, None).with_origin_agent_cluster(false);
+		assert_eq!("?0", response.headers.unwrap()["Origin-Agent-Cluster"]);
+	}
+
+	#[test]
+	fn test_with_timing_allow_origin() {
+		let response = HttpResponse::ok(None, None).with_timing_allow_origin("*");
+		assert_eq!("*", response.headers.unwrap()["Timing-Allow-Origin"]);
+
+		let response = HttpResponse::ok(None, None).with_timing_allow_origin("https://example.com");
+		assert_eq!(
+			"https://example.com",
+			response.headers.unwrap()["Timing-Allow-Origin"]
+		);
+	}
+
+	#[test]
+	fn test_with_nel_and_report_to() {
+		let response = HttpResponse::ok(None, None)
+			.with_nel(86400, "network-errors")
+			.with_report_to(
+				"network-errors",
+				86400,
+				&[
+					"https://reports.example/nel",
+					"https://backup.example/\"q\""
+				]
+			);
+		let headers = response.headers.unwrap();
+
+		assert_eq!(
+			"{\"report_to\":\"network-errors\",\"max_age\":86400}",
+			headers["NEL"]
+		);
+		assert_eq!(
+			"{\"group\":\"network-errors\",\"max_age\":86400,\"endpoints\":[{\"url\":\"https://reports.example/nel\"},{\"url\":\"https://backup.example/\\\"q\\\"\"}]}",
+			headers["Report-To"]
+		);
+	}
+
+	#[test]
+	fn test_with_csp_nonce() {
+		let (response, nonce) =
+			HttpResponse::ok(None, None).with_csp_nonce("default-src 'self'; script-src 'nonce-{nonce}'");
+		assert_eq!(
+			Some(format!("default-src 'self'; script-src 'nonce-{}'", nonce).as_str()),
+			response.header("Content-Security-Policy")
+		);
+		let (response, other) = response.with_csp_nonce("{nonce}");
+
+		assert_eq!(24, nonce.len());
+		assert_ne!(nonce, other);
+		assert_eq!(other, response.headers.unwrap()["Content-Security-Policy"]);
+
+		let (response, nonce) =
+			HttpResponse::ok(None, None).with_csp_nonce_from("'nonce-{nonce}'", [0xfb; 16]);
+		assert_eq!("+/v7+/v7+/v7+/v7+/v7+w==", nonce);
+		assert_eq!(
+			Some("'nonce-+/v7+/v7+/v7+/v7+/v7+w=='"),
+			response.header("Content-Security-Policy")
+		);
+	}
+
+	#[test]
+	fn test_base64() {
+		assert_eq!("", base64(b""));
+		assert_eq!("Zg==", base64(b"f"));
+		assert_eq!("Zm8=", base64(b"fo"));
+		assert_eq!("Zm9v", base64(b"foo"));
+		assert_eq!("Zm9vYmFy", base64(b"foobar"));
+	}
+
+	#[test]
+	fn test_user_content_length_overrides() {
+		let body = String::from("héllo 🦀");
+		assert_eq!(11, body.len());
+
+		let mut headers = HashMap::new();
+		headers.insert("content-length", "11".to_string());
+		let response: String = HttpResponse::ok(Some(headers), Some(body)).into();
+
+		assert_eq!(1, response.to_lowercase().matches("content-length").count());
+		assert!(response.ends_with("content-length:11\r\n\r\nhéllo 🦀"));
+
+		let response: String = HttpResponse::ok(None, Some("🦀".into())).into();
+		assert!(response.contains("Content-Length: 4\r\n"));
+	}
+
+	#[test]
+	fn test_form_urlencoded() {
+		let response = HttpResponse::form_urlencoded(&[("user", "ana"), ("next", "/a b&c")]);
+
+		assert_eq!("200", response.status_code);
+		assert_eq!(Some("user=ana&next=%2Fa%20b%26c".into()), response.body);
+		assert_eq!(
+			"application/x-www-form-urlencoded",
+			response.headers.unwrap()["Content-Type"]
+		);
+	}
+
+	#[test]
+	fn test_builder() {
+		let response = HttpResponse::builder().build().unwrap();
+		assert_eq!(("200", "OK"), (response.status_code, response.status_text));
+		assert_eq!("text/plain", response.headers.unwrap()["Content-Type"]);
+
+		let response = HttpResponse::builder().status(299).build().unwrap();
+		assert_eq!(("299", ""), (response.status_code, response.status_text));
+		assert!(response.to_string().starts_with("HTTP/1.1 299 \r\n"));
+		let status = |builder: HttpResponseBuilder<'static>| {
+			let response = builder.build().unwrap();
+			(response.status_code, response.status_text)
 		};
+		assert_eq!(
+			("599", "Custom"),
+			status(HttpResponse::builder().status(599).reason("Custom"))
+		);
+		assert_eq!(
+			("100", "Continue"),
+			status(HttpResponse::builder().status(100))
+		);
 
-		response.body = body;
-		response
+		for code in [0, 99, 600, 999] {
+			assert_eq!(
+				Err(SerializeError::InvalidStatus(code)),
+				HttpResponse::builder().status(code).build()
+			);
+		}
+		assert_eq!(
+			Ok("201"),
+			HttpResponse::builder()
+				.status(600)
+				.status(201)
+				.build()
+				.map(|res| res.status_code)
+		);
+		assert_eq!(
+			Err(SerializeError::InvalidReason),
+			HttpResponse::builder()
+				.reason("OK\r\nX-Injected: 1")
+				.build()
+		);
+
+		let response = HttpResponse::builder()
+			.header_if(false, "X-Debug", "1")
+			.header_opt("ETag", None)
+			.header_if(true, "X-Cache", "hit")
+			.header_opt("Vary", Some("Accept".into()))
+			.build()
+			.unwrap();
+		assert_eq!(None, response.header("X-Debug"));
+		assert_eq!(None, response.header("ETag"));
+		assert_eq!(Some("hit"), response.header("X-Cache"));
+		assert_eq!(Some("Accept"), response.header("Vary"));
+
+		let mut sent = Vec::new();
+		HttpResponse::builder()
+			.status(201)
+			.body("created")
+			.send(&mut sent)
+			.unwrap();
+		assert_eq!(
+			&b"HTTP/1.1 201 Created\r\nContent-Type:text/plain\r\nContent-Length: 7\r\n\r\ncreated"[..],
+			sent
+		);
+
+		let mut sent = Vec::new();
+		let err = HttpResponse::builder()
+			.status(42)
+			.send(&mut sent)
+			.unwrap_err();
+		assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+		assert!(sent.is_empty());
+
+		let mut builder = HttpResponse::builder().status(404);
+		for (name, value) in [("X-A", "1"), ("x-a", "2"), ("content-type", "text/html")] {
+			builder = builder.header(name, value);
+		}
+		let response = builder.body(String::from("missing")).build().unwrap();
+		let headers = response.headers.as_ref().unwrap();
+
+		assert_eq!("Not Found", response.status_text);
+		assert_eq!(Some("missing".into()), response.body);
+		assert_eq!(2, headers.len());
+		assert_eq!("2", headers["x-a"]);
+		assert_eq!("text/html", headers["content-type"]);
 	}
 
-	pub fn send_response(&self, stream: &mut impl Write) -> Result<()> {
-		write!(stream, "{}", self)
+	#[test]
+	fn test_with_bytes() {
+		let png = vec![0x89, b'P', b'N', b'G', 0x00, 0xff];
+		let mut headers = HashMap::new();
+		headers.insert("Content-Type", "image/png".to_string());
+		let response = HttpResponse::ok(Some(headers), Some("replaced".into())).with_bytes(png.clone());
+		assert_eq!(None, response.body);
+		assert_eq!(Some(&png[..]), response.body_bytes());
+		assert_eq!(response, response.clone());
+
+		let mut sent = Vec::new();
+		response.send_response(&mut sent).unwrap();
+		assert_eq!(sent, response.into_bytes());
+
+		let mut expected =
+			b"HTTP/1.1 200 OK\r\nContent-Type:image/png\r\nContent-Length: 6\r\n\r\n".to_vec();
+		expected.extend_from_slice(&png);
+		assert_eq!(expected, sent);
+
+		let text = HttpResponse::ok(None, Some("text".into()));
+		assert_eq!(String::from(&text).into_bytes(), text.into_bytes());
+
+		let lossy = String::from(HttpResponse::ok(None, None).with_bytes(png));
+		assert!(lossy.ends_with("Content-Length: 10\r\n\r\n\u{fffd}PNG\0\u{fffd}"));
+	}
+
+	/// Records the bytes written between flushes.
+	#[derive(Default)]
+	struct FlushRecorder {
+		pending: Vec<u8>,
+		flushed: Vec<String>
+	}
+
+	impl Write for FlushRecorder {
+		fn write(&mut self, buf: &[u8]) -> Result<usize> {
+			self.pending.extend_from_slice(buf);
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> Result<()> {
+			let pending = std::mem::take(&mut self.pending);
+			self.flushed.push(String::from_utf8(pending).unwrap());
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_send_streaming() {
+		let response = HttpResponse::ok(None, Some("data: hi\n\n".into()));
+		let mut recorder = FlushRecorder::default();
+		response.send_streaming(&mut recorder).unwrap();
+
+		assert_eq!(2, recorder.flushed.len());
+		assert_eq!(
+			format!("HTTP/1.1 200 OK\r\n{}", response.headers_block()),
+			recorder.flushed[0]
+		);
+		assert_eq!("data: hi\n\n", recorder.flushed[1]);
+
+		let mut response = HttpResponse::ok(None, None);
+		let data = "x".repeat(10000);
+		response.set_body_reader(io::Cursor::new(data.clone()), data.len());
+		let mut recorder = FlushRecorder::default();
+		response.send_streaming(&mut recorder).unwrap();
+
+		assert_eq!(3, recorder.flushed.len());
+		assert!(recorder.flushed[0].ends_with("Content-Length: 10000\r\n\r\n"));
+		assert_eq!(data, recorder.flushed[1..].concat());
+	}
+
+	#[test]
+	fn test_text() {
+		let response = HttpResponse::text("hello".into(), "utf-8");
+		assert_eq!("200", response.status_code);
+		assert_eq!(Some("hello".into()), response.body);
+		assert_eq!(
+			"text/plain; charset=utf-8",
+			response.headers.unwrap()["Content-Type"]
+		);
+
+		let response = HttpResponse::text("hello".into(), "latin1\r\nX-Injected: 1");
+		assert_eq!(
+			"text/plain; charset=utf-8",
+			response.headers.unwrap()["Content-Type"]
+		);
 	}
 }
 
-impl<'a> HttpResponse<'a> {
-	fn headers(&self) -> String {
-		let map = &self.headers;
-		let mut header_string = String::new();
+/// Fluent construction of a response, see `HttpResponse::builder`.
+/// ```
+/// use http::response::HttpResponse;
+///
+/// let builder = HttpResponse::builder().status(201).header("Location", "/items/7");
+/// let response = builder.body("created").build().unwrap();
+/// assert_eq!("201", response.status_code);
+/// assert_eq!("text/plain", response.headers.unwrap()["Content-Type"]);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct HttpResponseBuilder<'a> {
+	response: HttpResponse<'a>,
+	infer_content_type: bool,
+	/// A code passed to `status` that can't be sent, reported by `build`.
+	invalid_status: Option<u16>
+}
 
-		for (k, v) in map.as_ref().unwrap().iter() {
-			header_string += &format!("{}{}:{}\r\n", header_string, k, v);
+impl<'a> HttpResponseBuilder<'a> {
+	/// Sets the status from its code, with the registered reason phrase or an empty
+	/// one for other codes, see `reason`. A code outside `100..=599` makes `build` fail.
+	/// ```
+	/// use http::response::{HttpResponse, SerializeError};
+	///
+	/// let response = HttpResponse::builder().status(299).reason("Custom").build().unwrap();
+	/// assert_eq!(("299", "Custom"), (response.status_code, response.status_text));
+	///
+	/// let invalid = HttpResponse::builder().status(999).build();
+	/// assert_eq!(Err(SerializeError::InvalidStatus(999)), invalid);
+	/// ```
+	pub fn status(mut self, code: u16) -> Self {
+		match status_code_str(code) {
+			Some(status_code) => {
+				self.response.status_code = status_code;
+				self.response.status_text = status_for(code).map_or("", |(_, text)| text);
+				self.invalid_status = None;
+			}
+			None => self.invalid_status = Some(code)
 		}
+		self
+	}
 
-		header_string
+	/// Sets the reason phrase sent after the status code. One with control
+	/// characters makes `build` fail.
+	pub fn reason(mut self, reason: &'a str) -> Self {
+		self.response.status_text = reason;
+		self
 	}
 
-	fn body(&self) -> &str {
-		match &self.body {
-			Some(b) => b.as_str(),
-			None => ""
+	/// Sets a header, replacing any value set before under the same name.
+	pub fn header(mut self, name: &'a str, value: impl Into<String>) -> Self {
+		self.response.set_header(name, value.into());
+		self
+	}
+
+	/// Sets a header only when `cond` holds, so handlers can stay one chain.
+	pub fn header_if(self, cond: bool, name: &'a str, value: impl Into<String>) -> Self {
+		if cond {
+			self.header(name, value)
+		} else {
+			self
 		}
 	}
-}
 
-impl<'a> From<HttpResponse<'a>> for String {
-	#[inline(always)]
-	fn from(res: HttpResponse) -> String {
-		String::from(&res)
+	/// Sets a header only when `value` is `Some`.
+	/// ```
+	/// use http::response::HttpResponse;
+	///
+	/// let etag: Option<String> = None;
+	/// let response = HttpResponse::builder().header_opt("ETag", etag).build().unwrap();
+	/// assert_eq!(None, response.header("ETag"));
+	/// ```
+	pub fn header_opt(self, name: &'a str, value: Option<String>) -> Self {
+		match value {
+			Some(value) => self.header(name, value),
+			None => self
+		}
 	}
-}
 
-impl<'a> From<&HttpResponse<'a>> for String {
-	fn from(res: &HttpResponse) -> String {
-		format!(
-			"{} {} {}\r\n{}Content-Length: {}\r\n\r\n{}",
-			res.version,
-			res.status_code,
-			res.status_text,
-			res.headers(),
-			res.body().len(),
-			res.body()
-		)
+	pub fn body(mut self, body: impl Into<String>) -> Self {
+		self.response.body = Some(body.into());
+		self
 	}
-}
 
-impl<'a> Display for HttpResponse<'a> {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", String::from(self))
+	/// Sniffs the content type from the body when `build` is called, see
+	/// `HttpResponse::with_inferred_content_type`.
+	pub fn infer_content_type(mut self) -> Self {
+		self.infer_content_type = true;
+		self
+	}
+
+	/// The response, with `Content-Type: text/plain` unless a content type was set
+	/// or inferred. Fails with `SerializeError::InvalidStatus` if `status` was given
+	/// a code outside `100..=599`, or `InvalidReason` for a reason phrase that
+	/// couldn't be sent.
+	pub fn build(mut self) -> std::result::Result<HttpResponse<'a>, SerializeError> {
+		if let Some(code) = self.invalid_status {
+			return Err(SerializeError::InvalidStatus(code));
+		}
+		if self
+			.response
+			.status_text
+			.bytes()
+			.any(|b| b != b'\t' && b.is_ascii_control())
+		{
+			return Err(SerializeError::InvalidReason);
+		}
+
+		if self.infer_content_type {
+			self.response = self.response.with_inferred_content_type();
+		}
+		if self.response.header("Content-Type").is_none() {
+			self
+				.response
+				.set_header("Content-Type", "text/plain".to_string());
+		}
+		Ok(self.response)
+	}
+
+	/// Builds the response and writes it to `w` with `HttpResponse::send_response`.
+	/// A response that `build` refuses is an `InvalidInput` error and nothing is written.
+	pub fn send(self, w: &mut impl Write) -> Result<()> {
+		let response = self
+			.build()
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+		response.send_response(w).map(|_| ())
 	}
 }
 
-#[cfg(test)]
-mod tests {
-	use crate::response::*;
+/// Formats an access log entry in the NCSA common log format:
+/// `ip - - [date] "METHOD target VERSION" status bytes`, dated now.
+pub fn common_log(
+	req: &HttpRequest,
+	resp: &HttpResponse,
+	client_ip: &str,
+	bytes_sent: usize
+) -> String {
+	let bytes = match bytes_sent {
+		0 => "-".to_string(),
+		n => n.to_string()
+	};
 
-	#[test]
-	fn test_response_struct_creation_200() {
-		let response_actual = HttpResponse::ok(None, Some("Item was shipped on 21st Dec 2020".into()));
+	format!(
+		"{} - - [{}] \"{} {} {}\" {} {}",
+		client_ip,
+		format_clf(SystemTime::now()),
+		req.method,
+		req.resource.as_str(),
+		req.version.as_str(),
+		resp.status_code,
+		bytes
+	)
+}
 
-		let response_expected = HttpResponse {
-			version: "HTTP/1.1",
-			status_code: "200",
-			status_text: "OK",
-			headers: {
-				let mut h = HashMap::new();
-				h.insert("Content-Type", "text/html".to_string());
-				Some(h)
-			},
-			body: Some("Item was shipped on 21st Dec 2020".into())
-		};
+/// Decides whether to let a client with `Expect: 100-continue` send its body, based
+/// on the declared `Content-Length`: a bare `100 Continue` when it fits in
+/// `max_bytes`, or a `413 Payload Too Large` so the body is never sent.
+///
+/// Without an `Expect` header the client sends its body anyway, so there's nothing
+/// to answer and `None` is returned: read the body and respond as usual. Any other
+/// expectation can't be met and gets a `417 Expectation Failed`.
+/// ```
+/// use http::request::HttpRequest;
+/// use http::response::should_continue;
+///
+/// let req = HttpRequest::from("PUT /upload HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n".to_string());
+/// assert_eq!("HTTP/1.1 100 Continue\r\n\r\n", should_continue(&req, 1024).unwrap().to_string());
+/// ```
+pub fn should_continue<'a>(req: &HttpRequest, max_bytes: usize) -> Option<HttpResponse<'a>> {
+	let expect = req.header("Expect")?;
+	if !expect.trim().eq_ignore_ascii_case("100-continue") {
+		return Some(HttpResponse::from_status(
+			None,
+			None,
+			"417",
+			"Expectation Failed"
+		));
+	}
 
-		assert_eq!(response_actual, response_expected);
+	let declared = req
+		.header("Content-Length")
+		.and_then(|len| len.trim().parse::<usize>().ok());
+
+	Some(match declared {
+		Some(len) if len > max_bytes => HttpResponse::payload_too_large(None, None),
+		// An interim response, so no Content-Type and no body.
+		_ => HttpResponse {
+			status_code: "100",
+			status_text: "Continue",
+			..HttpResponse::default()
+		}
+	})
+}
+
+/// The codes `100` to `599` back to back, so that any status the builder accepts
+/// has a `&'static str` for `status_code`.
+static STATUS_CODES: [u8; 1500] = {
+	let mut digits = [0; 1500];
+	let mut i = 0;
+	while i < 500 {
+		let code = i + 100;
+		digits[i * 3] = b'0' + (code / 100) as u8;
+		digits[i * 3 + 1] = b'0' + (code / 10 % 10) as u8;
+		digits[i * 3 + 2] = b'0' + (code % 10) as u8;
+		i += 1;
 	}
+	digits
+};
 
-	#[test]
-	fn test_response_struct_creation_404() {
-		let response_actual =
-			HttpResponse::not_found(None, Some("Item was shipped on 21st Dec 2020".into()));
+/// `code` as three digits, `None` outside `100..=599`.
+fn status_code_str(code: u16) -> Option<&'static str> {
+	let start = usize::from(code.checked_sub(100)?) * 3;
+	let digits = STATUS_CODES.get(start..start + 3)?;
+	std::str::from_utf8(digits).ok()
+}
 
-		let response_expected = HttpResponse {
-			version: "HTTP/1.1",
-			status_code: "404",
-			status_text: "Not Found",
-			headers: {
-				let mut h = HashMap::new();
-				h.insert("Content-Type", "text/html".to_string());
-				Some(h)
-			},
-			body: Some("Item was shipped on 21st Dec 2020".into())
-		};
+/// Whether `s` is a valid token (RFC 7230 `tchar`s), as required for charset names.
+fn is_token(s: &str) -> bool {
+	!s.is_empty()
+		&& s
+			.bytes()
+			.all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
 
-		assert_eq!(response_actual, response_expected);
+/// Formats `s` as a quoted string, escaping backslashes and double quotes.
+fn quote(s: &str) -> String {
+	format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 16 unpredictable bytes without a dependency or a platform-specific source. Each
+/// `RandomState` has SipHash keys derived from the OS random source, so the hash of
+/// an empty input can't be guessed without them.
+fn random_bytes() -> [u8; 16] {
+	let mut bytes = [0u8; 16];
+	for half in bytes.chunks_mut(8) {
+		let hash = RandomState::new().build_hasher().finish();
+		half.copy_from_slice(&hash.to_le_bytes());
 	}
+	bytes
+}
 
-	#[test]
-	fn test_http_response_creation() {
-		let response_expected = HttpResponse {
-			version: "HTTP/1.1",
-			status_code: "404",
-			status_text: "Not Found",
-			headers: {
-				let mut h = HashMap::new();
-				h.insert("Content-Type", "text/html".to_string());
-				Some(h)
-			},
-			body: Some("Item was shipped on 21st Dec 2020".into())
-		};
+/// Standard padded base64.
+fn base64(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
-		let http_string: String = response_expected.into();
-		let response_actual = "HTTP/1.1 404 Not Found\r\nContent-Type:text/html\r\nContent-Length: 33\r\n\r\nItem was shipped on 21st Dec 2020";
+	let mut out = String::new();
+	for chunk in bytes.chunks(3) {
+		let n = chunk
+			.iter()
+			.enumerate()
+			.fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
 
-		assert_eq!(http_string, response_actual);
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+/// Formats `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+	let mut out = String::from("\"");
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c)
+		}
 	}
+	out.push('"');
+	out
+}
+
+/// Status code and reason phrase for a numeric status, if it's a registered one.
+fn status_for(code: u16) -> Option<(&'static str, &'static str)> {
+	Some(match code {
+		100 => ("100", "Continue"),
+		101 => ("101", "Switching Protocols"),
+		102 => ("102", "Processing"),
+		103 => ("103", "Early Hints"),
+		200 => ("200", "OK"),
+		201 => ("201", "Created"),
+		202 => ("202", "Accepted"),
+		203 => ("203", "Non-Authoritative Information"),
+		204 => ("204", "No Content"),
+		205 => ("205", "Reset Content"),
+		206 => ("206", "Partial Content"),
+		207 => ("207", "Multi-Status"),
+		208 => ("208", "Already Reported"),
+		226 => ("226", "IM Used"),
+		300 => ("300", "Multiple Choices"),
+		301 => ("301", "Moved Permanently"),
+		302 => ("302", "Found"),
+		303 => ("303", "See Other"),
+		304 => ("304", "Not Modified"),
+		305 => ("305", "Use Proxy"),
+		307 => ("307", "Temporary Redirect"),
+		308 => ("308", "Permanent Redirect"),
+		400 => ("400", "Bad Request"),
+		401 => ("401", "Unauthorized"),
+		402 => ("402", "Payment Required"),
+		403 => ("403", "Forbidden"),
+		404 => ("404", "Not Found"),
+		405 => ("405", "Method Not Allowed"),
+		406 => ("406", "Not Acceptable"),
+		407 => ("407", "Proxy Authentication Required"),
+		408 => ("408", "Request Timeout"),
+		409 => ("409", "Conflict"),
+		410 => ("410", "Gone"),
+		411 => ("411", "Length Required"),
+		412 => ("412", "Precondition Failed"),
+		413 => ("413", "Payload Too Large"),
+		414 => ("414", "URI Too Long"),
+		415 => ("415", "Unsupported Media Type"),
+		416 => ("416", "Range Not Satisfiable"),
+		417 => ("417", "Expectation Failed"),
+		418 => ("418", "I'm a teapot"),
+		421 => ("421", "Misdirected Request"),
+		422 => ("422", "Unprocessable Entity"),
+		423 => ("423", "Locked"),
+		424 => ("424", "Failed Dependency"),
+		425 => ("425", "Too Early"),
+		426 => ("426", "Upgrade Required"),
+		428 => ("428", "Precondition Required"),
+		429 => ("429", "Too Many Requests"),
+		431 => ("431", "Request Header Fields Too Large"),
+		451 => ("451", "Unavailable For Legal Reasons"),
+		500 => ("500", "Internal Server Error"),
+		501 => ("501", "Not Implemented"),
+		502 => ("502", "Bad Gateway"),
+		503 => ("503", "Service Unavailable"),
+		504 => ("504", "Gateway Timeout"),
+		505 => ("505", "HTTP Version Not Supported"),
+		506 => ("506", "Variant Also Negotiates"),
+		507 => ("507", "Insufficient Storage"),
+		508 => ("508", "Loop Detected"),
+		510 => ("510", "Not Extended"),
+		511 => ("511", "Network Authentication Required"),
+		_ => return None
+	})
 }
 
 impl<'a> HttpResponse<'a> {
@@ -248,6 +2299,17 @@ impl<'a> HttpResponse<'a> {
 		Self::from_status(headers, body, "206", "Partial Content")
 	}
 
+	pub fn im_used(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
+		Self::from_status(headers, body, "226", "IM Used")
+	}
+
+	pub fn moved_permanently(
+		headers: Option<HashMap<&'a str, String>>,
+		body: Option<String>
+	) -> Self {
+		Self::from_status(headers, body, "301", "Moved Permanently")
+	}
+
 	pub fn found(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
 		Self::from_status(headers, body, "302", "Found")
 	}
@@ -282,6 +2344,19 @@ impl<'a> HttpResponse<'a> {
 		Self::from_status(headers, body, "401", "Unauthorized")
 	}
 
+	/// A `401` with a `WWW-Authenticate: Bearer` challenge for `realm`, plus an
+	/// `error` such as `invalid_token` when the client sent a bad token.
+	pub fn unauthorized_bearer(realm: &str, error: Option<&str>) -> Self {
+		let mut challenge = format!("Bearer realm={}", quote(realm));
+		if let Some(error) = error {
+			challenge += &format!(", error={}", quote(error));
+		}
+
+		let mut response = Self::unauthorized(None, None);
+		response.set_header("WWW-Authenticate", challenge);
+		response
+	}
+
 	pub fn forbidden(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
 		Self::from_status(headers, body, "403", "Forbidden")
 	}
@@ -301,18 +2376,62 @@ impl<'a> HttpResponse<'a> {
 		Self::from_status(headers, body, "408", "Request Timeout")
 	}
 
+	pub fn conflict(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
+		Self::from_status(headers, body, "409", "Conflict")
+	}
+
 	pub fn gone(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
 		Self::from_status(headers, body, "410", "Gone")
 	}
 
+	pub fn payload_too_large(
+		headers: Option<HashMap<&'a str, String>>,
+		body: Option<String>
+	) -> Self {
+		Self::from_status(headers, body, "413", "Payload Too Large")
+	}
+
+	pub fn uri_too_long(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
+		Self::from_status(headers, body, "414", "URI Too Long")
+	}
+
 	pub fn im_a_teapot(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
 		Self::from_status(headers, body, "418", "I'm a teapot")
 	}
 
+	pub fn unprocessable_entity(
+		headers: Option<HashMap<&'a str, String>>,
+		body: Option<String>
+	) -> Self {
+		Self::from_status(headers, body, "422", "Unprocessable Entity")
+	}
+
+	pub fn too_many_requests(
+		headers: Option<HashMap<&'a str, String>>,
+		body: Option<String>
+	) -> Self {
+		Self::from_status(headers, body, "429", "Too Many Requests")
+	}
+
 	pub fn internal_server_error(
 		headers: Option<HashMap<&'a str, String>>,
 		body: Option<String>
 	) -> Self {
 		Self::from_status(headers, body, "500", "Internal Server Error")
 	}
+
+	pub fn bad_gateway(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
+		Self::from_status(headers, body, "502", "Bad Gateway")
+	}
+
+	pub fn service_unavailable(
+		headers: Option<HashMap<&'a str, String>>,
+		body: Option<String>
+	) -> Self {
+		Self::from_status(headers, body, "503", "Service Unavailable")
+	}
+
+	pub fn gateway_timeout(headers: Option<HashMap<&'a str, String>>, body: Option<String>) -> Self {
+		Self::from_status(headers, body, "504", "Gateway Timeout")
+	}
 }
@@ -0,0 +1,94 @@
+//! Parsing of `text/event-stream` bodies, for consuming server-sent events.
+
+/// A single server-sent event.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SseEvent {
+	/// Event type from the `event` field, `None` for the default `message` type.
+	pub event: Option<String>,
+	/// The `data` lines, joined with `\n`.
+	pub data: String,
+	pub id: Option<String>,
+	/// Reconnection time in milliseconds.
+	pub retry: Option<u64>
+}
+
+/// Parses a `text/event-stream` body into its events. Events are separated by blank
+/// lines, lines starting with `:` are comments and an unfinished trailing event (with
+/// no blank line after it) is dropped, as the stream may still be incomplete.
+/// ```
+/// use http::sse::parse_sse;
+///
+/// let events = parse_sse("event: ping\ndata: 1\n\n");
+/// assert_eq!(Some("ping".to_string()), events[0].event);
+/// assert_eq!("1", events[0].data);
+/// ```
+pub fn parse_sse(body: &str) -> Vec<SseEvent> {
+	let mut events = Vec::new();
+	let mut current = SseEvent::default();
+	let mut data_lines: Vec<&str> = Vec::new();
+	let mut has_fields = false;
+
+	for line in body.lines() {
+		if line.is_empty() {
+			if has_fields {
+				current.data = data_lines.join("\n");
+				events.push(std::mem::take(&mut current));
+				data_lines.clear();
+				has_fields = false;
+			}
+			continue;
+		}
+
+		if line.starts_with(':') {
+			continue;
+		}
+
+		let (field, value) = match line.split_once(':') {
+			Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+			None => (line, "")
+		};
+
+		match field {
+			"event" => current.event = Some(value.to_string()),
+			"data" => data_lines.push(value),
+			"id" => current.id = Some(value.to_string()),
+			"retry" => match value.parse() {
+				Ok(retry) => current.retry = Some(retry),
+				Err(_) => continue
+			},
+			_ => continue
+		}
+		has_fields = true;
+	}
+
+	events
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::sse::*;
+
+	#[test]
+	fn test_parse_sse() {
+		let body = ": keep-alive\n\ndata: first\ndata: line\nid: 1\n\nevent: update\ndata: {\"x\": 1}\nretry: 3000\n\ndata: unfinished";
+		let events = parse_sse(body);
+
+		assert_eq!(
+			vec![
+				SseEvent {
+					event: None,
+					data: "first\nline".into(),
+					id: Some("1".into()),
+					retry: None
+				},
+				SseEvent {
+					event: Some("update".into()),
+					data: "{\"x\": 1}".into(),
+					id: None,
+					retry: Some(3000)
+				}
+			],
+			events
+		);
+	}
+}
@@ -0,0 +1,234 @@
+//! Percent-encoding helpers for request targets and form bodies.
+
+/// Percent-encodes every byte of `s` except the unreserved characters
+/// (`A-Z a-z 0-9 - . _ ~`).
+/// ```
+/// use http::url::percent_encode;
+///
+/// assert_eq!("a%20b%2Fc", percent_encode("a b/c"));
+/// ```
+pub fn percent_encode(s: &str) -> String {
+	let mut encoded = String::with_capacity(s.len());
+
+	for b in s.bytes() {
+		if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+			encoded.push(b as char);
+		} else {
+			encoded += &format!("%{:02X}", b);
+		}
+	}
+
+	encoded
+}
+
+/// Percent-encodes one path segment. A `/` inside the segment is encoded too, so it
+/// can't be mistaken for a separator.
+pub fn encode_path_segment(s: &str) -> String {
+	percent_encode(s)
+}
+
+/// Encodes each of `segments` and joins them with `/`.
+/// ```
+/// use http::url::encode_path;
+///
+/// assert_eq!("files/my%20notes/a%2Fb.txt", encode_path(&["files", "my notes", "a/b.txt"]));
+/// ```
+pub fn encode_path(segments: &[&str]) -> String {
+	segments
+		.iter()
+		.map(|segment| encode_path_segment(segment))
+		.collect::<Vec<_>>()
+		.join("/")
+}
+
+/// Joins `pairs` into a `key=value&...` string, percent-encoding keys and values.
+pub fn encode_pairs(pairs: &[(&str, &str)]) -> String {
+	pairs
+		.iter()
+		.map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+		.collect::<Vec<_>>()
+		.join("&")
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+	(b as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Decodes `%XX` escapes into bytes, and `+` into a space if `plus_as_space` is set.
+/// Malformed escapes are kept as they are.
+fn decode_bytes(input: &str, plus_as_space: bool) -> Vec<u8> {
+	let bytes = input.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+
+	while i < bytes.len() {
+		match bytes[i] {
+			b'%' => {
+				let escape = bytes
+					.get(i + 1)
+					.and_then(|h| hex_value(*h))
+					.zip(bytes.get(i + 2).and_then(|l| hex_value(*l)));
+				match escape {
+					Some((high, low)) => {
+						decoded.push(high << 4 | low);
+						i += 3;
+						continue;
+					}
+					None => decoded.push(b'%')
+				}
+			}
+			b'+' if plus_as_space => decoded.push(b' '),
+			b => decoded.push(b)
+		}
+		i += 1;
+	}
+
+	decoded
+}
+
+/// Decodes `%XX` escapes, interpreting the result as UTF-8 (invalid sequences are
+/// replaced). `+` is left alone and malformed escapes are kept as they are.
+/// ```
+/// use http::url::percent_decode;
+///
+/// assert_eq!("my document.txt", percent_decode("my%20document.txt"));
+/// ```
+pub fn percent_decode(input: &str) -> String {
+	String::from_utf8_lossy(&decode_bytes(input, false)).into_owned()
+}
+
+/// Parses `application/x-www-form-urlencoded` data such as a form body or a query
+/// string into decoded pairs, in order. `+` decodes to a space, a key without `=`
+/// gets an empty value and empty pairs are skipped.
+/// ```
+/// use http::url::parse_urlencoded;
+///
+/// let pairs = parse_urlencoded("a=1&b=two+words&c=");
+/// assert_eq!(("b".to_string(), "two words".to_string()), pairs[1]);
+/// ```
+pub fn parse_urlencoded(input: &str) -> Vec<(String, String)> {
+	parse_urlencoded_bytes(input)
+		.into_iter()
+		.map(|(k, v)| (decode_charset(&k, "utf-8"), decode_charset(&v, "utf-8")))
+		.collect()
+}
+
+/// Same as `parse_urlencoded`, leaving the decoded keys and values as raw bytes.
+pub(crate) fn parse_urlencoded_bytes(input: &str) -> Vec<(Vec<u8>, Vec<u8>)> {
+	input
+		.split('&')
+		.filter(|pair| !pair.is_empty())
+		.map(|pair| {
+			let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+			(decode_bytes(key, true), decode_bytes(value, true))
+		})
+		.collect()
+}
+
+/// Interprets `bytes` in `charset`. ISO-8859-1 (latin1) and US-ASCII are decoded
+/// byte for byte; anything else is treated as UTF-8, replacing invalid sequences.
+pub(crate) fn decode_charset(bytes: &[u8], charset: &str) -> String {
+	match charset.trim().to_ascii_lowercase().as_str() {
+		"iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "us-ascii" | "ascii" => {
+			bytes.iter().map(|b| *b as char).collect()
+		}
+		_ => String::from_utf8_lossy(bytes).into_owned()
+	}
+}
+
+/// Lowercases the scheme and host of an origin such as `HTTP://Example.COM:8080`,
+/// leaving the port and anything after the host untouched, so equivalent origins
+/// compare equal.
+/// ```
+/// use http::url::normalize_origin;
+///
+/// assert_eq!("http://example.com:8080", normalize_origin("HTTP://Example.COM:8080"));
+/// ```
+pub fn normalize_origin(origin: &str) -> String {
+	let origin = origin.trim();
+	let authority_start = origin.find("://").map_or(0, |i| i + 3);
+	let authority_end = origin[authority_start..]
+		.find('/')
+		.map_or(origin.len(), |i| authority_start + i);
+
+	format!(
+		"{}{}",
+		origin[..authority_end].to_ascii_lowercase(),
+		&origin[authority_end..]
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::url::*;
+
+	#[test]
+	fn test_percent_encode() {
+		assert_eq!("plain-text_1.0~", percent_encode("plain-text_1.0~"));
+		assert_eq!("a%20b%26c%3Dd", percent_encode("a b&c=d"));
+		assert_eq!("%C3%A9", percent_encode("é"));
+	}
+
+	#[test]
+	fn test_encode_pairs() {
+		assert_eq!(
+			"q=rust%20lang&page=2",
+			encode_pairs(&[("q", "rust lang"), ("page", "2")])
+		);
+		assert_eq!("", encode_pairs(&[]));
+	}
+
+	#[test]
+	fn test_encode_path() {
+		assert_eq!("my%20file.txt", encode_path_segment("my file.txt"));
+		assert_eq!("a%2Fb", encode_path_segment("a/b"));
+		assert_eq!("docs/a%2Fb/c%20d", encode_path(&["docs", "a/b", "c d"]));
+		assert_eq!("", encode_path(&[]));
+	}
+
+	#[test]
+	fn test_normalize_origin() {
+		assert_eq!(
+			normalize_origin("http://example.com"),
+			normalize_origin("HTTP://Example.COM")
+		);
+		assert_eq!(
+			"https://example.com:8443",
+			normalize_origin("HTTPS://EXAMPLE.com:8443")
+		);
+		assert_eq!(
+			"http://example.com/Path",
+			normalize_origin("http://EXAMPLE.com/Path")
+		);
+		assert_eq!("null", normalize_origin("null"));
+	}
+
+	#[test]
+	fn test_percent_decode() {
+		assert_eq!("a b/c", percent_decode("a%20b%2Fc"));
+		assert_eq!("a+b", percent_decode("a+b"));
+		assert_eq!("é", percent_decode("%C3%A9"));
+		assert_eq!("%2 %zz %", percent_decode("%2%20%zz%20%"));
+	}
+
+	#[test]
+	fn test_parse_urlencoded() {
+		let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+
+		assert_eq!(
+			vec![pair("a", "1"), pair("b", "two words"), pair("c", "")],
+			parse_urlencoded("a=1&b=two+words&c=")
+		);
+		assert_eq!(
+			vec![pair("flag", ""), pair("x y", "a=b&c")],
+			parse_urlencoded("flag&&x%20y=a%3Db%26c")
+		);
+	}
+
+	#[test]
+	fn test_decode_charset() {
+		assert_eq!("café", decode_charset(b"caf\xe9", "ISO-8859-1"));
+		assert_eq!("café", decode_charset("café".as_bytes(), "utf-8"));
+		assert_eq!("caf\u{fffd}", decode_charset(b"caf\xe9", "unknown"));
+	}
+}